@@ -1,7 +1,11 @@
-use bevy::sprite::Rect;
+use std::{collections::BTreeMap, ops::Range, str::FromStr};
+
+use hashbrown::HashMap;
+
+use bevy::{sprite::Rect, reflect::TypeUuid};
 use serde::{Deserialize, Serialize};
 
-use crate::{chunky::{Chunk, CHUNK_SIZE}, identifier::Identifier};
+use crate::{block_entity::BlockEntityKind, chunky::{Chunk, CHUNK_SIZE}, connected_texture::*, identifier::{Identifier, InternedId}, model::{BakedQuad, ModelFace}, procedural::Climate, tint::{BlockTint, Colormaps, NO_TINT}, registry::{RawId, StateId, BlockRegistry, BlockStateRegistry, RegistryLoadReport, LoadProblem, normalize_id}, BlockyPathError};
 
 pub const FACE_INDICES: &[u32; 6] = &[
     0, 1, 2, // triangle 1
@@ -50,6 +54,16 @@ pub const VERTICES_BACK: &[([f32;3], [f32;3]); 4] = &[
     ([-0.5,  0.5, 0.5], [0., 0., 1.]),
 ];
 
+/// How far each texture layer is drawn in front of the one below it,
+/// so coplanar layers don't flicker
+pub const LAYER_OFFSET: f32 = 0.001;
+
+/// Brightest light level a block can give off
+pub const MAX_LUMINANCE: u8 = 15;
+
+pub const DEFAULT_HARDNESS: f32 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub enum BlockFace {
     Top,
     Bottom,
@@ -59,10 +73,276 @@ pub enum BlockFace {
     Back
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::Top,
+        BlockFace::Bottom,
+        BlockFace::Left,
+        BlockFace::Right,
+        BlockFace::Front,
+        BlockFace::Back,
+    ];
+
+    /// Vertices of this face on a full cube
+    pub fn get_vertices(&self) -> &'static [([f32; 3], [f32; 3]); 4] {
+        match self {
+            BlockFace::Top => VERTICES_TOP,
+            BlockFace::Bottom => VERTICES_BOTTOM,
+            BlockFace::Left => VERTICES_LEFT,
+            BlockFace::Right => VERTICES_RIGHT,
+            BlockFace::Front => VERTICES_FRONT,
+            BlockFace::Back => VERTICES_BACK,
+        }
+    }
+
+    /// The `VoxelCullCode` for this side of a block
+    pub fn cull_code(&self) -> u8 {
+        match self {
+            BlockFace::Top => VoxelCullCode::U as u8,
+            BlockFace::Bottom => VoxelCullCode::D as u8,
+            BlockFace::Left => VoxelCullCode::L as u8,
+            BlockFace::Right => VoxelCullCode::R as u8,
+            BlockFace::Front => VoxelCullCode::F as u8,
+            BlockFace::Back => VoxelCullCode::B as u8,
+        }
+    }
+
+    /// Direction this face points to, out of the block
+    pub fn get_direction(&self) -> [i32; 3] {
+        match self {
+            BlockFace::Top => [0, 1, 0],
+            BlockFace::Bottom => [0, -1, 0],
+            BlockFace::Left => [1, 0, 0],
+            BlockFace::Right => [-1, 0, 0],
+            BlockFace::Front => [0, 0, -1],
+            BlockFace::Back => [0, 0, 1],
+        }
+    }
+
+    /// Vertices of texture layer `layer` of this face on a full cube,
+    /// pushed out of the cube so it's drawn over the layers below it
+    pub fn get_layer_vertices(&self, layer: usize) -> [([f32; 3], [f32; 3]); 4] {
+        let offset = self.get_direction().map(|axis| axis as f32 * LAYER_OFFSET * (layer + 1) as f32);
+
+        self.get_vertices().map(|(position, normal)| {
+            ([0, 1, 2].map(|axis| position[axis] + offset[axis]), normal)
+        })
+    }
+
+    /// Position of this face in `BlockFace::ALL`
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Directions pointing to the right and to the top of this face's texture,
+    /// matching how its UVs are laid out
+    pub fn get_texture_axes(&self) -> ([i32; 3], [i32; 3]) {
+        match self {
+            BlockFace::Top => ([-1, 0, 0], [0, 0, -1]),
+            BlockFace::Bottom => ([1, 0, 0], [0, 0, 1]),
+            BlockFace::Left | BlockFace::Right => ([0, 0, -1], [0, 1, 0]),
+            BlockFace::Front | BlockFace::Back => ([-1, 0, 0], [0, 1, 0]),
+        }
+    }
+
+    /// Returns the face named `name` (`top`, `bottom`, ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "top" => Some(BlockFace::Top),
+            "bottom" => Some(BlockFace::Bottom),
+            "left" => Some(BlockFace::Left),
+            "right" => Some(BlockFace::Right),
+            "front" => Some(BlockFace::Front),
+            "back" => Some(BlockFace::Back),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "e5a5fecd-b731-4d39-8a88-b709b211d583"]
 pub struct BlockDefinition {
     pub id: String,
 
+    /// ID of a block this block copies every unset field from
+    #[serde(default)]
+    pub parent: String,
+
+    #[serde(default)]
+    pub texture: String,
+
+    #[serde(default)]
+    pub top_texture: String,
+    #[serde(default)]
+    pub bottom_texture: String,
+    #[serde(default)]
+    pub left_texture: String,
+    #[serde(default)]
+    pub right_texture: String,
+    #[serde(default)]
+    pub front_texture: String,
+    #[serde(default)]
+    pub back_texture: String,
+
+    /// ID of the model under `data/<namespace>/models` this block is drawn with.<br>
+    /// Blocks without a model are drawn as full cubes
+    #[serde(default)]
+    pub model: String,
+
+    /// Color the block's faces are tinted with
+    #[serde(default)]
+    pub tint: Option<BlockTint>,
+
+    /// Faces `tint` applies to. Every face is tinted if empty
+    #[serde(default)]
+    pub tinted_faces: Vec<BlockFace>,
+
+    /// Textures every face picks from by its position in the world,
+    /// and the rotations it can be drawn with.<br>
+    /// Faces listed in `random_faces` use theirs instead
+    #[serde(default)]
+    pub random_texture: Option<RandomTexture>,
+
+    /// `random_texture` for single faces
+    #[serde(default)]
+    pub random_faces: BTreeMap<BlockFace, RandomTexture>,
+
+    /// Textures drawn over the faces' own textures, bottom layer first.<br>
+    /// Only full cube faces get layers
+    #[serde(default)]
+    pub layers: Vec<TextureLayer>,
+
+    /// Data kept for every placed copy of this block, like the items in a chest
+    #[serde(default)]
+    pub block_entity: Option<BlockEntityKind>,
+
+    /// Whether an item that places this block is registered along with it. Defaults to `true`
+    #[serde(default)]
+    pub has_item: Option<bool>,
+
+    /// Whether the faces of this block join up with the same block next to them,
+    /// using the `.ctm.png` sheet of each face's texture. Defaults to `false`
+    #[serde(default)]
+    pub connected_textures: Option<bool>,
+
+    /// Properties every state of this block has a value for.<br>
+    /// One state is registered for every combination of values
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyKind>,
+
+    /// Texture overrides for states matching `when`.<br>
+    /// Later variants win over earlier ones
+    #[serde(default)]
+    pub variants: Vec<StateVariant>,
+
+    /// How long the block takes to mine; negative values can't be mined.<br>
+    /// Defaults to 1
+    #[serde(default)]
+    pub hardness: Option<f32>,
+    /// Lowest tier of an effective tool the block drops its item for.<br>
+    /// Defaults to dropping it for anything, even a bare hand
+    #[serde(default)]
+    pub required_tier: Option<u32>,
+    /// How well the block withstands explosions. Defaults to its hardness
+    #[serde(default)]
+    pub blast_resistance: Option<f32>,
+    /// Whether the block hides the faces next to it and blocks light.<br>
+    /// Defaults to `true`, unless the block is transparent
+    #[serde(default)]
+    pub opaque: Option<bool>,
+    /// Whether the block's textures have see-through pixels. Defaults to `false`
+    #[serde(default)]
+    pub transparent: Option<bool>,
+    /// Light level the block gives off, up to `MAX_LUMINANCE`. Defaults to 0
+    #[serde(default)]
+    pub luminance: Option<u8>,
+    /// Whether entities bump into the block. Defaults to `true`
+    #[serde(default)]
+    pub collidable: Option<bool>,
+    /// Whether placed blocks and worldgen can overwrite the block. Defaults to `false`
+    #[serde(default)]
+    pub replaceable: Option<bool>,
+}
+
+/// A texture drawn over the textures of some faces, like ore speckles over stone
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TextureLayer {
+    pub texture: String,
+
+    /// Faces this layer is drawn on. Every face if empty
+    #[serde(default)]
+    pub faces: Vec<BlockFace>,
+
+    /// Whether the block's `tint` colors this layer
+    #[serde(default)]
+    pub tinted: bool,
+}
+
+impl TextureLayer {
+    pub fn is_on_face(&self, block_face: BlockFace) -> bool {
+        self.faces.is_empty() || self.faces.contains(&block_face)
+    }
+}
+
+/// Textures a face picks from by its position in the world,
+/// so that fields of the same block don't tile visibly
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RandomTexture {
+    /// Textures to pick from instead of the face's own texture.<br>
+    /// The face keeps its own texture if empty
+    #[serde(default)]
+    pub textures: Vec<WeightedTexture>,
+
+    /// Angles in degrees the texture can be rotated by, in steps of 90.<br>
+    /// The texture is never rotated if empty
+    #[serde(default)]
+    pub rotations: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WeightedTexture {
+    pub texture: String,
+
+    /// How likely this texture is to be picked, compared to the others. Defaults to 1
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 { 1 }
+
+/// The values a block property can have
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum PropertyKind {
+    Bool,
+    Int {
+        min: i32,
+        max: i32,
+    },
+    Enum(Vec<String>),
+}
+
+impl PropertyKind {
+    /// Every value of this property, as written in the definition.<br>
+    /// The first value is used by the default state
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            PropertyKind::Bool => vec![String::from("false"), String::from("true")],
+            PropertyKind::Int { min, max } => (*min..=*max).map(|value| value.to_string()).collect(),
+            PropertyKind::Enum(values) => values.clone(),
+        }
+    }
+}
+
+/// Overrides textures and the model for every state
+/// whose property values match `when`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StateVariant {
+    pub when: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub model: String,
+
+    /// Used for every face that isn't overridden below
     #[serde(default)]
     pub texture: String,
 
@@ -80,7 +360,143 @@ pub struct BlockDefinition {
     pub back_texture: String,
 }
 
+impl StateVariant {
+    pub fn matches(&self, state_values: &BTreeMap<String, String>) -> bool {
+        self.when.iter().all(|(name, value)| state_values.get(name) == Some(value))
+    }
+
+    fn get_texture_for_face(&self, block_face: BlockFace) -> Option<&String> {
+        let texture_face = match block_face {
+            BlockFace::Top => &self.top_texture,
+            BlockFace::Bottom => &self.bottom_texture,
+            BlockFace::Left => &self.left_texture,
+            BlockFace::Right => &self.right_texture,
+            BlockFace::Front => &self.front_texture,
+            BlockFace::Back => &self.back_texture,
+        };
+
+        if !texture_face.is_empty() {
+            Some(texture_face)
+        } else if !self.texture.is_empty() {
+            Some(&self.texture)
+        } else {
+            None
+        }
+    }
+}
+
 impl BlockDefinition {
+    /// Returns this definition with every unset field taken from `parent`.<br>
+    /// Physical properties are taken from the parent when not set here.<br>
+    /// A face set here, or this `texture`, replaces the parent's face, and
+    /// `texture` falls back to the parent's.<br>
+    /// Properties are merged, and variants are added after the parent's
+    pub fn inherit(&self, parent: &BlockDefinition) -> BlockDefinition {
+        let get_face = |face: &String, parent_face: &String| {
+            pick_face_texture(face, &self.texture, parent_face).to_string()
+        };
+
+        let mut properties = parent.properties.clone();
+        properties.extend(self.properties.clone());
+
+        let mut variants = parent.variants.clone();
+        variants.extend(self.variants.clone());
+
+        let mut layers = parent.layers.clone();
+        layers.extend(self.layers.clone());
+
+        let mut random_faces = parent.random_faces.clone();
+        random_faces.extend(self.random_faces.clone());
+
+        BlockDefinition {
+            id: self.id.clone(),
+            parent: self.parent.clone(),
+            texture: if self.texture.is_empty() { parent.texture.clone() } else { self.texture.clone() },
+            top_texture: get_face(&self.top_texture, &parent.top_texture),
+            bottom_texture: get_face(&self.bottom_texture, &parent.bottom_texture),
+            left_texture: get_face(&self.left_texture, &parent.left_texture),
+            right_texture: get_face(&self.right_texture, &parent.right_texture),
+            front_texture: get_face(&self.front_texture, &parent.front_texture),
+            back_texture: get_face(&self.back_texture, &parent.back_texture),
+            model: if self.model.is_empty() { parent.model.clone() } else { self.model.clone() },
+            tint: self.tint.or(parent.tint),
+            tinted_faces: if self.tinted_faces.is_empty() { parent.tinted_faces.clone() } else { self.tinted_faces.clone() },
+            random_texture: self.random_texture.clone().or_else(|| parent.random_texture.clone()),
+            random_faces,
+            layers,
+            block_entity: self.block_entity.or(parent.block_entity),
+            has_item: self.has_item.or(parent.has_item),
+            connected_textures: self.connected_textures.or(parent.connected_textures),
+            properties,
+            variants,
+            hardness: self.hardness.or(parent.hardness),
+            required_tier: self.required_tier.or(parent.required_tier),
+            blast_resistance: self.blast_resistance.or(parent.blast_resistance),
+            opaque: self.opaque.or(parent.opaque),
+            transparent: self.transparent.or(parent.transparent),
+            luminance: self.luminance.or(parent.luminance),
+            collidable: self.collidable.or(parent.collidable),
+            replaceable: self.replaceable.or(parent.replaceable),
+        }
+    }
+
+    /// Every property of this block, in state numbering order
+    pub fn get_properties(&self) -> Vec<BlockProperty> {
+        self.properties.iter()
+            .map(|(name, kind)| BlockProperty {
+                name: name.clone(),
+                values: kind.values(),
+            })
+            .collect()
+    }
+
+    /// Returns how the texture of `block_face` is randomized, if it is
+    pub fn get_random_texture(&self, block_face: BlockFace) -> Option<&RandomTexture> {
+        self.random_faces.get(&block_face).or(self.random_texture.as_ref())
+    }
+
+    /// Returns the ID of the model the state with `state_values` is drawn with,
+    /// or `None` if it is a full cube
+    pub fn get_model_for_state(&self, state_values: &BTreeMap<String, String>) -> Option<&str> {
+        let model = self.variants.iter()
+            .rev()
+            .filter(|variant| variant.matches(state_values) && !variant.model.is_empty())
+            .map(|variant| &variant.model)
+            .next()
+            .unwrap_or(&self.model);
+
+        if model.is_empty() {
+            None
+        } else {
+            Some(model)
+        }
+    }
+
+    /// Returns the texture for a face of a model, drawn on the side `block_face`
+    /// of the state with `state_values`
+    pub fn get_texture_for_model_face(&self, model_face: &ModelFace, block_face: BlockFace, state_values: &BTreeMap<String, String>) -> Option<String> {
+        match model_face.texture.strip_prefix('#') {
+            Some(face_name) => self.get_texture_for_state(BlockFace::from_name(face_name)?, state_values),
+            None if model_face.texture.is_empty() => self.get_texture_for_state(block_face, state_values),
+            None => Some(format!("textures/block/{}.png", model_face.texture)),
+        }
+    }
+
+    /// Returns the texture for a face of the state with `state_values`,
+    /// taking any matching variants into account
+    pub fn get_texture_for_state(&self, block_face: BlockFace, state_values: &BTreeMap<String, String>) -> Option<String> {
+        // later variants win over earlier ones
+        let variant_texture = self.variants.iter()
+            .rev()
+            .filter(|variant| variant.matches(state_values))
+            .find_map(|variant| variant.get_texture_for_face(block_face));
+
+        match variant_texture {
+            Some(texture) => Some(format!("textures/block/{}.png", texture)),
+            None => self.get_texture_for_face(block_face)
+        }
+    }
+
     pub fn get_texture_for_face(&self, block_face: BlockFace) -> Option<String> {
         match Identifier::from_str(&self.id) {
             Ok(id) => {
@@ -91,19 +507,11 @@ impl BlockDefinition {
                     BlockFace::Right => &self.right_texture,
                     BlockFace::Front => &self.front_texture,
                     BlockFace::Back => &self.back_texture,
-                }.clone();
-        
-                let texture = if !self.texture.is_empty() {
-                    self.texture.clone()
-                } else {
-                    if texture_face.is_empty() {
-                        id.get_name()
-                    } else {
-                        texture_face
-                    }
                 };
-        
-                Some(format!("textures/block\\{}.png", texture))
+
+                let texture = pick_face_texture(texture_face, &self.texture, &id.get_name()).to_string();
+
+                Some(format!("textures/block/{}.png", texture))
             },
             Err(err) => {
                 println!("[Error] {err}");
@@ -113,6 +521,115 @@ impl BlockDefinition {
     }
 }
 
+/// Returns the first texture set out of `face_texture`, `texture` and `fallback`.<br>
+/// Faces pick their texture in this order both when inheriting and when rendering
+fn pick_face_texture<'a>(face_texture: &'a str, texture: &'a str, fallback: &'a str) -> &'a str {
+    [face_texture, texture, fallback].into_iter()
+        .find(|texture| !texture.is_empty())
+        .unwrap_or_default()
+}
+
+/// Applies `parent` inheritance to every block definition,
+/// given as `(file path, definition)`.<br>
+/// Definitions are returned parents first, so registering them
+/// in order registers a parent before any of its children.<br>
+/// Definitions with a missing parent or a cycle of parents
+/// are added to `report` and left out
+pub fn resolve_block_definitions(
+    block_defs: &[(String, &BlockDefinition)],
+    report: &mut RegistryLoadReport,
+) -> Vec<(String, BlockDefinition)> {
+    let ids = block_defs.iter().enumerate()
+        .map(|(index, (_, block_def))| (normalize_id(&block_def.id), index))
+        .collect::<HashMap<String, usize>>();
+
+    let mut resolved = HashMap::new();
+    let mut order = Vec::new();
+
+    for index in 0..block_defs.len() {
+        resolve_block_definition(index, block_defs, &ids, report, &mut resolved, &mut order, &mut Vec::new());
+    }
+
+    order.into_iter()
+        .filter_map(|index| {
+            let block_def = resolved.remove(&index)??;
+
+            Some((block_defs[index].0.clone(), block_def))
+        })
+        .collect()
+}
+
+fn resolve_block_definition(
+    index: usize,
+    block_defs: &[(String, &BlockDefinition)],
+    ids: &HashMap<String, usize>,
+    report: &mut RegistryLoadReport,
+    resolved: &mut HashMap<usize, Option<BlockDefinition>>,
+    order: &mut Vec<usize>,
+    stack: &mut Vec<usize>,
+) -> Option<BlockDefinition> {
+    if let Some(block_def) = resolved.get(&index) {
+        return block_def.clone();
+    }
+
+    let (path, block_def) = &block_defs[index];
+
+    // this block is already being resolved further down, so it is its own parent
+    if let Some(start) = stack.iter().position(|child| *child == index) {
+        let mut cycle = stack[start..].iter()
+            .map(|child| block_defs[*child].1.id.clone())
+            .collect::<Vec<String>>();
+        cycle.push(block_def.id.clone());
+
+        report.add(LoadProblem::ParentCycle {
+            path: path.clone(),
+            block: block_def.id.clone(),
+            cycle: cycle.join(" -> "),
+        });
+
+        return None;
+    }
+
+    let result = match block_def.parent.as_str() {
+        "" => Some((*block_def).clone()),
+        parent => {
+            stack.push(index);
+
+            let parent_def = match Identifier::from_str(parent) {
+                Ok(parent_id) => match ids.get(&parent_id.to_string()) {
+                    Some(parent_index) => resolve_block_definition(*parent_index, block_defs, ids, report, resolved, order, stack),
+                    None => {
+                        report.add(LoadProblem::MissingParent {
+                            path: path.clone(),
+                            block: block_def.id.clone(),
+                            parent: String::from(parent),
+                        });
+
+                        None
+                    }
+                },
+                Err(err) => {
+                    report.add(BlockyPathError::InvalidId(path.clone(), err));
+
+                    None
+                }
+            };
+
+            stack.pop();
+
+            parent_def.map(|parent_def| block_def.inherit(&parent_def))
+        }
+    };
+
+    if result.is_some() {
+        order.push(index);
+    }
+
+    resolved.insert(index, result.clone());
+
+    result
+}
+
 #[derive(Debug, Clone)]
 pub struct TextureCoords {
     pub bottom_left_x: f32,
@@ -122,9 +639,116 @@ pub struct TextureCoords {
     pub top_right_y: f32
 }
 
+/// A property of a block, and every value it can have
+#[derive(Debug, Clone)]
+pub struct BlockProperty {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
 #[derive(Clone)]
 pub struct Block {
-    pub(crate) id: Identifier,
+    pub(crate) id: InternedId,
+
+    pub(crate) properties: Vec<BlockProperty>,
+
+    /// State ID of the first state of this block;
+    /// every other state directly follows it
+    pub(crate) first_state: StateId,
+
+    pub(crate) hardness: f32,
+    pub(crate) required_tier: Option<u32>,
+    pub(crate) blast_resistance: f32,
+    pub(crate) opaque: bool,
+    pub(crate) transparent: bool,
+    pub(crate) luminance: u8,
+    pub(crate) collidable: bool,
+    pub(crate) replaceable: bool,
+
+    pub(crate) block_entity: Option<BlockEntityKind>,
+}
+
+impl Block {
+    pub fn get_identifier(&self) -> Identifier { self.id.get().clone() }
+
+    /// The interned ID of this block, for comparing without allocating
+    pub fn get_id(&self) -> InternedId { self.id }
+
+    pub fn get_properties(&self) -> &[BlockProperty] { &self.properties }
+
+    /// Number of states this block has; one for every combination of property values
+    pub fn get_state_count(&self) -> usize {
+        self.properties.iter().map(|property| property.values.len()).product()
+    }
+
+    /// Every state ID belonging to this block
+    pub fn get_states(&self) -> Range<StateId> {
+        self.first_state..self.first_state + self.get_state_count() as StateId
+    }
+
+    /// The state where every property has its first value
+    pub fn get_default_state(&self) -> StateId { self.first_state }
+
+    /// How long the block takes to mine, or a negative value if it can't be mined
+    pub fn get_hardness(&self) -> f32 { self.hardness }
+
+    pub fn is_unbreakable(&self) -> bool { self.hardness < 0. }
+
+    /// Lowest tier of an effective tool the block drops its item for, if any is needed
+    pub fn get_required_tier(&self) -> Option<u32> { self.required_tier }
+
+    pub fn get_blast_resistance(&self) -> f32 { self.blast_resistance }
+
+    /// Returns `true` if the block hides the faces next to it and blocks light
+    pub fn is_opaque(&self) -> bool { self.opaque }
+
+    /// Returns `true` if the block's textures have see-through pixels
+    pub fn is_transparent(&self) -> bool { self.transparent }
+
+    /// Light level the block gives off, from 0 to `MAX_LUMINANCE`
+    pub fn get_luminance(&self) -> u8 { self.luminance }
+
+    pub fn is_collidable(&self) -> bool { self.collidable }
+
+    /// Returns `true` if placed blocks and worldgen can overwrite this block
+    pub fn is_replaceable(&self) -> bool { self.replaceable }
+
+    /// The kind of block entity every placed copy of this block gets, if any
+    pub fn get_block_entity_kind(&self) -> Option<BlockEntityKind> { self.block_entity }
+
+    /// Returns the state with the given property values.<br>
+    /// Properties that aren't given use their first value
+    pub fn get_state_id(&self, values: &[(&str, &str)]) -> Option<StateId> {
+        let mut state_index = 0;
+
+        for (name, _) in values {
+            if !self.properties.iter().any(|property| property.name == *name) {
+                return None;
+            }
+        }
+
+        // the last property changes fastest between neighbouring states
+        for property in &self.properties {
+            let value_index = match values.iter().find(|(name, _)| *name == property.name) {
+                Some((_, value)) => property.values.iter().position(|x| x == value)?,
+                None => 0
+            };
+
+            state_index = state_index * property.values.len() + value_index;
+        }
+
+        Some(self.first_state + state_index as StateId)
+    }
+}
+
+/// One combination of property values of a block,
+/// along with everything needed to draw it
+#[derive(Clone)]
+pub struct BlockState {
+    pub(crate) block: RawId,
+
+    /// Value of every property of the block, by name
+    pub(crate) values: BTreeMap<String, String>,
 
     pub(crate) texture_front: Rect, //TextureCoords,
     pub(crate) texture_back: Rect, //TextureCoords,
@@ -132,76 +756,244 @@ pub struct Block {
     pub(crate) texture_btm: Rect, //TextureCoords,
     pub(crate) texture_left: Rect, //TextureCoords,
     pub(crate) texture_right: Rect, //TextureCoords,
+
+    /// Quads to draw instead of a full cube
+    pub(crate) model: Option<Vec<BakedQuad>>,
+
+    /// `VoxelCullCode`s of the sides this state covers completely
+    pub(crate) full_faces: u8,
+
+    pub(crate) tint: Option<BlockTint>,
+
+    /// `VoxelCullCode`s of the faces `tint` applies to
+    pub(crate) tinted_faces: u8,
+
+    /// Atlas coordinates of every connected variant of each face's texture,
+    /// indexed like `BlockFace::ALL`. Empty for faces that don't connect
+    pub(crate) connected_textures: [Vec<Rect>; 6],
+
+    /// Textures and rotations each face picks from, indexed like `BlockFace::ALL`
+    pub(crate) random_faces: [RandomFace; 6],
+
+    /// Layers drawn over each face, bottom layer first, indexed like `BlockFace::ALL`
+    pub(crate) layers: [Vec<FaceLayer>; 6],
 }
 
-impl Block {
-    pub fn get_identifier(&self) -> Identifier { self.id.clone() }
+/// A texture layer of a face, ready to be meshed
+#[derive(Clone)]
+pub struct FaceLayer {
+    pub(crate) tex_coords: Rect,
+    pub(crate) tinted: bool,
+}
+
+/// The atlas coordinates and rotations a face picks from by its position
+#[derive(Clone, Default)]
+pub struct RandomFace {
+    textures: Vec<(Rect, u32)>,
+    total_weight: u32,
+
+    /// Quarter turns the texture can be rotated by
+    rotations: Vec<u8>,
+}
+
+impl RandomFace {
+    /// Textures with a weight of 0 are never picked
+    pub fn new(textures: Vec<(Rect, u32)>, rotations: Vec<u8>) -> Self {
+        let textures = textures.into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .collect::<Vec<(Rect, u32)>>();
+
+        Self {
+            total_weight: textures.iter().map(|(_, weight)| weight).sum(),
+            textures,
+            rotations,
+        }
+    }
+
+    /// Returns `true` if the face always looks the same
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty() && self.rotations.is_empty()
+    }
+
+    /// Picks a texture and a number of quarter turns using `seed`.<br>
+    /// The texture is `None` if the face keeps its own
+    pub fn pick(&self, seed: u64) -> (Option<Rect>, u8) {
+        let mut roll = (seed % self.total_weight.max(1) as u64) as u32;
+
+        let texture = self.textures.iter().find_map(|(tex_coords, weight)| {
+            if roll < *weight {
+                Some(*tex_coords)
+            } else {
+                roll -= weight;
+                None
+            }
+        });
+
+        // the upper half of the seed, so the rotation doesn't follow the texture
+        let rotation = match self.rotations.len() {
+            0 => 0,
+            len => self.rotations[((seed >> 32) % len as u64) as usize],
+        };
+
+        (texture, rotation)
+    }
+}
+
+impl BlockState {
+    /// Runtime ID of the block this is a state of
+    pub fn get_block(&self) -> RawId { self.block }
 
-    pub fn get_uvs_top(&self) -> Vec<[f32;2]> { 
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
 
-        uvs.push([ self.texture_top.min.x, self.texture_top.min.y ]);
-        uvs.push([ self.texture_top.max.x, self.texture_top.min.y ]);
-        uvs.push([ self.texture_top.max.x, self.texture_top.max.y ]);
-        uvs.push([ self.texture_top.min.x, self.texture_top.max.y ]);
+    pub fn get_values(&self) -> &BTreeMap<String, String> { &self.values }
 
-        uvs
+    /// Returns the quads of this state's model, or `None` if it is a full cube
+    pub fn get_model(&self) -> Option<&[BakedQuad]> { self.model.as_deref() }
+
+    /// Returns the vertex color of `block_face` when this state is in `climate`
+    pub fn get_tint_color(&self, block_face: BlockFace, colormaps: &Colormaps, climate: Climate) -> [f32; 4] {
+        match self.tint {
+            Some(tint) if self.tinted_faces & block_face.cull_code() != 0 => colormaps.get_color(tint, climate),
+            _ => NO_TINT
+        }
     }
 
-    pub fn get_uvs_bottom(&self) -> Vec<[f32;2]> {
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+    /// Returns the layers drawn over `block_face`, bottom layer first
+    pub fn get_layers(&self, block_face: BlockFace) -> &[FaceLayer] {
+        &self.layers[block_face.index()]
+    }
+
+    /// Returns the vertex color of a layer when this state is in `climate`
+    pub fn get_layer_color(&self, layer: &FaceLayer, colormaps: &Colormaps, climate: Climate) -> [f32; 4] {
+        match self.tint {
+            Some(tint) if layer.tinted => colormaps.get_color(tint, climate),
+            _ => NO_TINT
+        }
+    }
 
-        uvs.push([ self.texture_btm.max.x, self.texture_btm.min.y ]);
-        uvs.push([ self.texture_btm.min.x, self.texture_btm.min.y ]);
-        uvs.push([ self.texture_btm.min.x, self.texture_btm.max.y ]);
-        uvs.push([ self.texture_btm.max.x, self.texture_btm.max.y ]);
+    /// Returns `true` if this state covers the whole side `block_face`
+    pub fn has_full_face(&self, block_face: BlockFace) -> bool {
+        self.full_faces & block_face.cull_code() != 0
+    }
 
-        uvs
+    /// Returns `true` if `block_face` joins up with the same block next to it
+    pub fn is_connected(&self, block_face: BlockFace) -> bool {
+        !self.connected_textures[block_face.index()].is_empty()
     }
 
-    pub fn get_uvs_left(&self) -> Vec<[f32;2]> {
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+    /// UVs of the connected variant of `block_face` to draw
+    /// with the neighbours in `connections`, from `connect_neighbors`
+    pub fn get_uvs_connected(&self, block_face: BlockFace, connections: u8) -> Vec<[f32;2]> {
+        let variants = &self.connected_textures[block_face.index()];
 
-        uvs.push([ self.texture_left.max.x, self.texture_left.min.y ]);
-        uvs.push([ self.texture_left.min.x, self.texture_left.min.y ]);
-        uvs.push([ self.texture_left.min.x, self.texture_left.max.y ]);
-        uvs.push([ self.texture_left.max.x, self.texture_left.max.y ]);
+        match variants.get(get_variant_index(connections)) {
+            Some(tex_coords) => get_face_uvs(block_face, tex_coords).to_vec(),
+            None => get_face_uvs(block_face, &self.get_face_texture(block_face)).to_vec()
+        }
+    }
 
-        uvs
+    /// Returns `true` if `block_face` picks its texture or rotation by its position
+    pub fn is_random(&self, block_face: BlockFace) -> bool {
+        !self.random_faces[block_face.index()].is_empty()
     }
 
-    pub fn get_uvs_right(&self) -> Vec<[f32;2]> {
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+    /// UVs of `block_face` with the texture and rotation
+    /// picked for `seed`, from `position_seed`
+    pub fn get_uvs_random(&self, block_face: BlockFace, seed: u64) -> Vec<[f32;2]> {
+        let (texture, rotation) = self.random_faces[block_face.index()].pick(seed);
 
-        uvs.push([ self.texture_right.min.x, self.texture_right.min.y ]);
-        uvs.push([ self.texture_right.max.x, self.texture_right.min.y ]);
-        uvs.push([ self.texture_right.max.x, self.texture_right.max.y ]);
-        uvs.push([ self.texture_right.min.x, self.texture_right.max.y ]);
+        let mut uvs = get_face_uvs(block_face, &texture.unwrap_or_else(|| self.get_face_texture(block_face)));
 
-        uvs
+        // every vertex taking the UV of the next one turns the texture a quarter
+        uvs.rotate_left(rotation as usize);
+
+        uvs.to_vec()
     }
 
-    pub fn get_uvs_front(&self) -> Vec<[f32;2]> {
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+    /// Atlas coordinates of the texture of `block_face`
+    pub fn get_face_texture(&self, block_face: BlockFace) -> Rect {
+        match block_face {
+            BlockFace::Top => self.texture_top,
+            BlockFace::Bottom => self.texture_btm,
+            BlockFace::Left => self.texture_left,
+            BlockFace::Right => self.texture_right,
+            BlockFace::Front => self.texture_front,
+            BlockFace::Back => self.texture_back,
+        }
+    }
+
+    pub fn get_uvs_top(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Top, &self.texture_top).to_vec() }
+
+    pub fn get_uvs_bottom(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Bottom, &self.texture_btm).to_vec() }
+
+    pub fn get_uvs_left(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Left, &self.texture_left).to_vec() }
 
-        uvs.push([ self.texture_front.max.x, self.texture_front.min.y ]);
-        uvs.push([ self.texture_front.min.x, self.texture_front.min.y ]);
-        uvs.push([ self.texture_front.min.x, self.texture_front.max.y ]);
-        uvs.push([ self.texture_front.max.x, self.texture_front.max.y ]);
+    pub fn get_uvs_right(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Right, &self.texture_right).to_vec() }
 
-        uvs
+    pub fn get_uvs_front(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Front, &self.texture_front).to_vec() }
+
+    pub fn get_uvs_back(&self) -> Vec<[f32;2]> { get_face_uvs(BlockFace::Back, &self.texture_back).to_vec() }
+}
+
+/// UVs for each vertex of a full cube face, showing `tex_coords`
+pub fn get_face_uvs(block_face: BlockFace, tex_coords: &Rect) -> [[f32; 2]; 4] {
+    let (min, max) = (tex_coords.min, tex_coords.max);
+
+    match block_face {
+        BlockFace::Top | BlockFace::Right => [
+            [ min.x, min.y ],
+            [ max.x, min.y ],
+            [ max.x, max.y ],
+            [ min.x, max.y ],
+        ],
+        BlockFace::Bottom | BlockFace::Left | BlockFace::Front => [
+            [ max.x, min.y ],
+            [ min.x, min.y ],
+            [ min.x, max.y ],
+            [ max.x, max.y ],
+        ],
+        BlockFace::Back => [
+            [ max.x, max.y ],
+            [ min.x, max.y ],
+            [ min.x, min.y ],
+            [ max.x, min.y ],
+        ],
     }
+}
 
-    pub fn get_uvs_back(&self) -> Vec<[f32;2]> {
-        let mut uvs: Vec<[f32;2]> = Vec::new();
+/// A seed for randomizing the face `block_face` of the block at the world position `(x, y, z)`.<br>
+/// Always the same for the same face, so remeshing and reloading keep every face as it was
+pub fn position_seed(x: i32, y: i32, z: i32, block_face: BlockFace) -> u64 {
+    let mut seed = (x as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as i64 as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+        ^ block_face.index() as u64;
 
-        uvs.push([ self.texture_back.max.x, self.texture_back.max.y ]);
-        uvs.push([ self.texture_back.min.x, self.texture_back.max.y ]);
-        uvs.push([ self.texture_back.min.x, self.texture_back.min.y ]);
-        uvs.push([ self.texture_back.max.x, self.texture_back.min.y ]);
+    // mix the bits so neighbouring positions get unrelated seeds
+    seed ^= seed >> 33;
+    seed = seed.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    seed ^= seed >> 33;
+    seed = seed.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    seed ^ (seed >> 33)
+}
+
+/// Every combination of values for `properties`, in state numbering order
+pub fn expand_states(properties: &[BlockProperty]) -> Vec<BTreeMap<String, String>> {
+    let mut states = vec![BTreeMap::new()];
 
-        uvs
+    for property in properties {
+        states = states.into_iter()
+            .flat_map(|state| property.values.iter().map(move |value| {
+                let mut state = state.clone();
+                state.insert(property.name.clone(), value.clone());
+                state
+            }))
+            .collect();
     }
+
+    states
 }
 
 // Credit: https://www.reddit.com/r/Unity3D/comments/5ys3vc/voxel_face_culling/desvzlu/
@@ -278,17 +1070,24 @@ pub enum VoxelCullCode
     BFUDRL = 63, //0011 1111
 }
 
-pub fn cull_neighbors(chunk: &Chunk, x: usize, y: usize, z: usize) -> u8 {
+/// Returns which faces of the block at `(x, y, z)` aren't hidden
+/// by the full face of an opaque neighbour
+pub fn cull_neighbors(
+    chunk: &Chunk,
+    block_registry: &BlockRegistry,
+    state_registry: &BlockStateRegistry,
+    x: usize, y: usize, z: usize
+) -> u8 {
     let mut code = 0;
 
     if x > 0 {
-        code = if chunk.has_block_at(x - 1, y, z) { code } else { code | (VoxelCullCode::R as u8) }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x - 1, y, z, BlockFace::Left) { code } else { code | (VoxelCullCode::R as u8) }
     } else {
         code |= VoxelCullCode::R as u8;
     }
 
     if z > 0 {
-        code = if chunk.has_block_at(x, y, z - 1) { code } else { code | VoxelCullCode::F as u8 }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x, y, z - 1, BlockFace::Back) { code } else { code | VoxelCullCode::F as u8 }
     }
     else {
         code |= VoxelCullCode::F as u8;
@@ -296,29 +1095,120 @@ pub fn cull_neighbors(chunk: &Chunk, x: usize, y: usize, z: usize) -> u8 {
 
 
     if x < CHUNK_SIZE - 1 {
-        code = if chunk.has_block_at(x + 1, y, z) { code } else { code | VoxelCullCode::L as u8 }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x + 1, y, z, BlockFace::Right) { code } else { code | VoxelCullCode::L as u8 }
     } else {
         code |= VoxelCullCode::L as u8;
     }
 
     if z < CHUNK_SIZE - 1 {
-        code = if chunk.has_block_at(x, y, z + 1) { code } else { code | VoxelCullCode::B as u8 }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x, y, z + 1, BlockFace::Front) { code } else { code | VoxelCullCode::B as u8 }
     } else {
         code |= VoxelCullCode::B as u8;
     }
 
 
     if y < CHUNK_SIZE - 1 {
-        code = if chunk.has_block_at(x, y + 1, z) { code } else { code | VoxelCullCode::U as u8 }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x, y + 1, z, BlockFace::Bottom) { code } else { code | VoxelCullCode::U as u8 }
     } else {
         code |= VoxelCullCode::U as u8;
     }
     
     if y > 0 {
-        code = if chunk.has_block_at(x, y - 1, z) { code } else { code | VoxelCullCode::D as u8 }
+        code = if chunk.has_full_face_at(block_registry, state_registry, x, y - 1, z, BlockFace::Top) { code } else { code | VoxelCullCode::D as u8 }
     } else {
         code |= VoxelCullCode::D as u8;
     }
 
     code
-}
\ No newline at end of file
+}
+/// Returns which of the 8 blocks around `block_face` of the block at `(x, y, z)`,
+/// as seen on the face's texture, are the same block as it.<br>
+/// Blocks outside of the chunk never connect
+pub fn connect_neighbors(
+    chunk: &Chunk,
+    state_registry: &BlockStateRegistry,
+    x: usize, y: usize, z: usize,
+    block_face: BlockFace
+) -> u8 {
+    let block = match chunk.get_block(state_registry, x, y, z) {
+        Some(state) => state.get_block(),
+        None => return 0
+    };
+
+    let (right, up) = block_face.get_texture_axes();
+
+    let is_same_block = |steps_right: i32, steps_up: i32| {
+        let [nx, ny, nz] = [0, 1, 2].map(|axis|
+            [x, y, z][axis] as i32 + right[axis] * steps_right + up[axis] * steps_up
+        );
+
+        let in_chunk = [nx, ny, nz].iter().all(|pos| (0..CHUNK_SIZE as i32).contains(pos));
+
+        in_chunk && chunk.get_block(state_registry, nx as usize, ny as usize, nz as usize)
+            .is_some_and(|state| state.get_block() == block)
+    };
+
+    let neighbours = [
+        (0, 1, CONNECT_UP),
+        (1, 0, CONNECT_RIGHT),
+        (0, -1, CONNECT_DOWN),
+        (-1, 0, CONNECT_LEFT),
+        (1, 1, CONNECT_UP_RIGHT),
+        (1, -1, CONNECT_DOWN_RIGHT),
+        (-1, -1, CONNECT_DOWN_LEFT),
+        (-1, 1, CONNECT_UP_LEFT),
+    ];
+
+    let connections = neighbours.into_iter()
+        .filter(|(steps_right, steps_up, _)| is_same_block(*steps_right, *steps_up))
+        .fold(0, |connections, (_, _, side)| connections | side);
+
+    simplify_connections(connections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(src: &str) -> BlockDefinition {
+        ron::from_str::<BlockDefinition>(src).unwrap()
+    }
+
+    fn texture(block_def: &BlockDefinition, block_face: BlockFace) -> String {
+        block_def.get_texture_for_face(block_face).unwrap()
+    }
+
+    #[test]
+    fn faces_win_over_texture_then_name() {
+        let block_def = definition(r#"(id: "blocky:log", texture: "bark", top_texture: "rings")"#);
+
+        assert_eq!(texture(&block_def, BlockFace::Top), "textures/block/rings.png");
+        assert_eq!(texture(&block_def, BlockFace::Front), "textures/block/bark.png");
+
+        let block_def = definition(r#"(id: "blocky:dirt")"#);
+
+        assert_eq!(texture(&block_def, BlockFace::Top), "textures/block/dirt.png");
+    }
+
+    #[test]
+    fn child_texture_replaces_parent_faces() {
+        let parent = definition(r#"(id: "blocky:grass", texture: "dirt", top_texture: "grass_top", front_texture: "grass_side")"#);
+        let child = definition(r#"(id: "blocky:painted_grass", parent: "blocky:grass", texture: "paint")"#)
+            .inherit(&parent);
+
+        for block_face in [BlockFace::Top, BlockFace::Front, BlockFace::Bottom] {
+            assert_eq!(texture(&child, block_face), "textures/block/paint.png");
+        }
+    }
+
+    #[test]
+    fn child_without_textures_renders_like_parent() {
+        let parent = definition(r#"(id: "blocky:grass", texture: "dirt", top_texture: "grass_top")"#);
+        let child = definition(r#"(id: "blocky:grass_slab", parent: "blocky:grass")"#)
+            .inherit(&parent);
+
+        for block_face in [BlockFace::Top, BlockFace::Front] {
+            assert_eq!(texture(&child, block_face), texture(&parent, block_face));
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{chunky::Chunk, identifier::Identifier, item_trait::Fuel, registry::ItemRegistry};
+
+/// The kind of data a block keeps for every placed copy of itself,
+/// set by `block_entity` in its definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlockEntityKind {
+    /// Holds items, like a chest
+    Container {
+        slots: usize,
+    },
+    /// Shows a few lines of text
+    Sign,
+    /// Burns fuel to smelt items
+    Furnace,
+}
+
+/// Number of lines of text on a sign
+pub const SIGN_LINES: usize = 4;
+
+/// Items stored in a block entity
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StoredItem {
+    pub id: String,
+    pub count: u32,
+}
+
+/// Data stored for a single placed block, beyond its state
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum BlockEntity {
+    Container {
+        slots: Vec<Option<StoredItem>>,
+    },
+    Sign {
+        lines: Vec<String>,
+    },
+    Furnace {
+        input: Option<StoredItem>,
+        fuel: Option<StoredItem>,
+        output: Option<StoredItem>,
+
+        /// Seconds left until the current fuel runs out
+        burn_time: f32,
+    },
+}
+
+impl BlockEntity {
+    /// An empty block entity of the given kind, for a block that was just placed
+    pub fn new(kind: BlockEntityKind) -> Self {
+        match kind {
+            BlockEntityKind::Container { slots } => BlockEntity::Container {
+                slots: vec![None; slots],
+            },
+            BlockEntityKind::Sign => BlockEntity::Sign {
+                lines: vec![String::new(); SIGN_LINES],
+            },
+            BlockEntityKind::Furnace => BlockEntity::Furnace {
+                input: None,
+                fuel: None,
+                output: None,
+                burn_time: 0.,
+            },
+        }
+    }
+
+    /// Returns `true` if this block entity holds data of the kind `kind`
+    pub fn is_kind(&self, kind: BlockEntityKind) -> bool {
+        matches!(
+            (self, kind),
+            (BlockEntity::Container { .. }, BlockEntityKind::Container { .. }) |
+            (BlockEntity::Sign { .. }, BlockEntityKind::Sign) |
+            (BlockEntity::Furnace { .. }, BlockEntityKind::Furnace)
+        )
+    }
+
+    /// Returns `true` if this is a furnace that is lit,
+    /// or that has something in its input and a `Fuel` item to light it with
+    pub fn can_burn(&self, item_registry: &ItemRegistry) -> bool {
+        match self {
+            BlockEntity::Furnace { input, fuel, burn_time, .. } =>
+                *burn_time > 0. || (input.is_some() && get_burn_time(fuel, item_registry).is_some()),
+            _ => false
+        }
+    }
+
+    /// Counts the fuel of a furnace down by `delta` seconds.<br>
+    /// Once it runs out, one `Fuel` item is taken from the fuel slot,
+    /// but only while there is something in the input to smelt
+    pub fn burn_fuel(&mut self, delta: f32, item_registry: &ItemRegistry) {
+        if let BlockEntity::Furnace { input, fuel, burn_time, .. } = self {
+            *burn_time = (*burn_time - delta).max(0.);
+
+            if *burn_time > 0. || input.is_none() {
+                return;
+            }
+
+            if let Some(fuel_time) = get_burn_time(fuel, item_registry) {
+                *burn_time = fuel_time;
+
+                if let Some(stored) = fuel {
+                    stored.count -= 1;
+
+                    if stored.count == 0 {
+                        *fuel = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Seconds the item in `fuel` burns for, if it has the `Fuel` trait
+fn get_burn_time(fuel: &Option<StoredItem>, item_registry: &ItemRegistry) -> Option<f32> {
+    let stored = fuel.as_ref().filter(|stored| stored.count > 0)?;
+    let id = Identifier::from_str(&stored.id).ok()?;
+
+    item_registry.get(&id)?
+        .get_trait::<Fuel>()
+        .map(|fuel| fuel.burn_time)
+        .filter(|burn_time| *burn_time > 0.)
+}
+
+/// Burns the fuel of every furnace that is lit or has something to smelt.<br>
+/// Smelting isn't done yet, so the input and output are left alone
+pub fn burn_furnace_fuel(
+    time: Res<Time>,
+    item_registry: Res<ItemRegistry>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let delta = time.delta_seconds();
+
+    for mut chunk in &mut chunks {
+        // only borrow the chunk mutably if something is burning,
+        // so idle chunks aren't marked as changed
+        let burning = chunk.block_entities()
+            .filter(|(_, block_entity)| block_entity.can_burn(&item_registry))
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+
+        for [x, y, z] in burning {
+            if let Some(block_entity) = chunk.get_block_entity_mut(x, y, z) {
+                block_entity.burn_fuel(delta, &item_registry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::item::ItemDefinition;
+
+    fn item_registry() -> ItemRegistry {
+        let mut item_registry = ItemRegistry::default();
+
+        item_registry.register(&Identifier::new("blocky", "coal"), ItemDefinition {
+            id: String::from("blocky:coal"),
+            stack_size: 64,
+            item_traits: vec![Box::new(Fuel { burn_time: 80. })],
+            texture: String::new(),
+        }).unwrap();
+
+        item_registry.register(&Identifier::new("blocky", "stone"), ItemDefinition {
+            id: String::from("blocky:stone"),
+            stack_size: 64,
+            item_traits: Vec::new(),
+            texture: String::new(),
+        }).unwrap();
+
+        item_registry
+    }
+
+    fn stored(id: &str, count: u32) -> Option<StoredItem> {
+        Some(StoredItem { id: String::from(id), count })
+    }
+
+    fn furnace(input: Option<StoredItem>, fuel: Option<StoredItem>) -> BlockEntity {
+        BlockEntity::Furnace { input, fuel, output: None, burn_time: 0. }
+    }
+
+    #[test]
+    fn furnaces_burn_fuel_items() {
+        let item_registry = item_registry();
+        let mut furnace = furnace(stored("blocky:stone", 1), stored("blocky:coal", 2));
+
+        assert!(furnace.can_burn(&item_registry));
+
+        furnace.burn_fuel(1., &item_registry);
+        assert_eq!(furnace, BlockEntity::Furnace {
+            input: stored("blocky:stone", 1),
+            fuel: stored("blocky:coal", 1),
+            output: None,
+            burn_time: 80.,
+        });
+
+        // the next item is only taken once the current one runs out
+        furnace.burn_fuel(30., &item_registry);
+        assert!(matches!(furnace, BlockEntity::Furnace { burn_time, .. } if burn_time == 50.));
+
+        furnace.burn_fuel(50., &item_registry);
+        assert!(matches!(&furnace, BlockEntity::Furnace { fuel: None, burn_time, .. } if *burn_time == 80.));
+    }
+
+    #[test]
+    fn furnaces_stay_out_without_input_or_fuel() {
+        let item_registry = item_registry();
+
+        assert!(!furnace(None, stored("blocky:coal", 1)).can_burn(&item_registry));
+        assert!(!furnace(stored("blocky:stone", 1), stored("blocky:stone", 1)).can_burn(&item_registry));
+
+        let mut empty = furnace(None, stored("blocky:coal", 1));
+        empty.burn_fuel(1., &item_registry);
+        assert_eq!(empty, furnace(None, stored("blocky:coal", 1)));
+    }
+}
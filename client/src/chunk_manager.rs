@@ -1,14 +1,27 @@
-use bevy::{tasks::{Task, AsyncComputeTaskPool}, prelude::{Component, Commands, Mesh, Query, Entity, ResMut, Assets, Res, Transform}, math::Vec3, pbr::{StandardMaterial, PbrBundle}, sprite::TextureAtlas};
+use bevy::{tasks::{Task, AsyncComputeTaskPool}, prelude::{Component, Commands, Mesh, Query, Entity, ResMut, Assets, Res, Transform}, math::Vec3, pbr::{StandardMaterial, PbrBundle, AlphaMode}, sprite::TextureAtlas};
 use futures_lite::future;
 
-use crate::{chunky::{Chunk, CHUNK_SIZE, build_chunk_mesh, ChunkMesh}, procedural::ProcGen, identifier::Identifier, registry::{get_block_from_registry, get_block_from_registry_by_string}, texture_atlas::TextureAtlasHandles, ToggleWireframe};
+use crate::{chunky::{Chunk, CHUNK_SIZE, build_chunk_mesh, ChunkMesh}, procedural::ProcGen, identifier::Identifier, registry::{BlockRegistry, BlockStateRegistry}, tint::Colormaps, texture_atlas::TextureAtlasHandles, ToggleWireframe};
 
 #[derive(Component)]
 pub struct ComputeChunk(Task<(Chunk, Mesh)>);
 
-pub fn spawn_ex_chunk_tasks(mut commands: Commands) {
+pub fn spawn_ex_chunk_tasks(
+    mut commands: Commands,
+    block_registry: Res<BlockRegistry>,
+    state_registry: Res<BlockStateRegistry>,
+    colormaps: Res<Colormaps>,
+) {
     let threadpool = AsyncComputeTaskPool::get();
 
+    let grass_block = match block_registry.get(&Identifier::new("blocky", "grass_block")) {
+        Some(grass_block) => grass_block.get_default_state(),
+        None => {
+            println!("[Error] \"blocky:grass_block\" isn't registered; can't generate chunks");
+            return;
+        }
+    };
+
     //let mut rng = rand::thread_rng();
 
     let genner = ProcGen::new(2342537, CHUNK_SIZE);
@@ -21,29 +34,35 @@ pub fn spawn_ex_chunk_tasks(mut commands: Commands) {
     for z in size_min..size_max {
         for x in size_min..size_max {
             for y in 0..1 {
+                let block_registry = block_registry.clone();
+                let state_registry = state_registry.clone();
+                let colormaps = colormaps.clone();
+
                 // spawn new task on the threadpool
                 let task = threadpool.spawn(async move {
-                    let block = get_block_from_registry_by_string("blocky:grass_block").unwrap();
-
                     let chunk_pos = Vec3::new(x as f32, y as f32, z as f32);
                     
                     let mut chunk = Chunk::new(chunk_pos);
                     let chunk_noise_map = genner.gen_noise_map(chunk_pos);
+
+                    chunk.set_climate_map(genner.gen_climate_map(chunk_pos));
                     
                     for z in 0..CHUNK_SIZE as i32 {
                         for x in 0..CHUNK_SIZE as i32 {
                             let y_pos = chunk_noise_map[z as usize * CHUNK_SIZE + x as usize];
                             
-                            chunk.add_block(
+                            chunk.place_block(
+                                &block_registry,
+                                &state_registry,
                                 x as usize,
                                 (y_pos) as usize,
                                 z as usize,
-                                Some(block.clone())
+                                Some(grass_block)
                             );
                         }
                     }
 
-                    let chunk_mesh = build_chunk_mesh(&chunk);
+                    let chunk_mesh = build_chunk_mesh(&chunk, &block_registry, &state_registry, &colormaps);
 
                     (chunk, chunk_mesh)
                 });
@@ -76,6 +95,8 @@ pub fn handle_chunk_tasks(
                     material: materials.add(
                         StandardMaterial {
                             base_color_texture: Some(texture_atlas.texture.clone()),
+                            // cut out the see-through pixels of overlays, glass and leaves
+                            alpha_mode: AlphaMode::Mask(0.5),
                             ..Default::default()
                     }),
                     ..Default::default()
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use bevy::{math::Vec3, prelude::*, render::mesh::Indices};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
 
-use crate::{block::*, registry, identifier::Identifier};
+use crate::{block::*, block_entity::BlockEntity, identifier::Identifier, registry::{BlockRegistry, BlockStateRegistry, StateId}, procedural::Climate, tint::Colormaps};
 
 pub const CHUNK_SIZE: usize = 16;
 
@@ -18,13 +22,20 @@ pub struct Chunk {
 
     chunk_pos: Vec3,
 
+    /// State IDs of the blocks in this chunk.<br>
     /// 0 is reserved for air:<br>
     /// Local block IDs are index + 1
-    ids: Vec<Option<String>>,
+    ids: Vec<Option<StateId>>,
 
     /// Blocks in this chunk.<br>
     /// Local block id is index + 1
     blocks: Vec<u16>,
+
+    /// Climate of every column, used to tint blocks
+    climate: Vec<Climate>,
+
+    /// Extra data of the blocks that have a block entity, by block index
+    block_entities: HashMap<usize, BlockEntity>,
 }
 
 /// used for storing a chunks mesh
@@ -42,6 +53,8 @@ impl Chunk {
 
             ids: Vec::new(),
             blocks: vec![0; CHUNK_SIZE.pow(3)],
+            climate: vec![Climate::default(); CHUNK_SIZE.pow(2)],
+            block_entities: HashMap::new(),
         }
     }
 
@@ -51,77 +64,57 @@ impl Chunk {
         self.is_empty
     }
 
-    pub fn add_block(&mut self, x: usize, y: usize, z: usize, block: Option<Block>) -> bool {
+    /// Writes the block state `block` at `(x, y, z)`,
+    /// or air if `block` is `None`.<br>
+    /// Only removing a block drops its block entity, so placing goes through `place_block`
+    fn add_block(&mut self, x: usize, y: usize, z: usize, block: Option<StateId>) -> bool {
         let pos = pos_as_index(x, y, z);
 
         if pos < self.blocks.len() {
-            if let Some(data) = &block {
-                self.is_empty = false;
-
-                if self.air_count > 0 {
+            if let Some(state_id) = block {
+                // only if we're placing into air
+                if !self.has_block_at(x, y, z) && self.air_count > 0 {
                     self.air_count -= 1;
                 }
 
-                let id_string = data.get_identifier().as_string();
-
                 // check if a block with this id exists already
                 // if not, add it to our collection
-                if !self.ids.contains(&Some(id_string.clone())) {
-                    // check if there are any free slots to place our block id
-                    let mut found_id = false;
-
-                    for (index, block_id) in self.ids.clone().into_iter().enumerate() {
-                        // if an empty spot was found,
-                        // insert our block id and exit the loop
-                        if let None = block_id {
-                            found_id = true;
-                            self.ids[index] = Some(id_string.clone());
-                            break;
-                        }
-                    }
-
-                    // if no free slots were found,
-                    // create a new one
-                    if !found_id {
-                        self.ids.push(Some(id_string.clone()))
+                if !self.ids.contains(&Some(state_id)) {
+                    // check if there are any free slots to place our block id,
+                    // if not, create a new one
+                    match self.ids.iter().position(|block_id| block_id.is_none()) {
+                        Some(index) => self.ids[index] = Some(state_id),
+                        None => self.ids.push(Some(state_id)),
                     }
                 }
 
                 // get the local numerical id for this block
                 let block_id = self.ids.iter().position(|x|
-                    x == &Some(id_string.clone())
+                    x == &Some(state_id)
                 ).unwrap() as u16 + 1;
 
                 // add it to our chunk at the correct position
                 self.blocks[pos] = block_id;
             } else {
                 // only if we're replacing a block
-                if let Some(_) = self.get_block(x, y, z) {
+                if self.has_block_at(x, y, z) {
                     self.air_count += 1;
 
                     let curr_block_id = self.get_local_block_id(x, y, z);
 
                     // add air
                     self.blocks[pos] = 0;
+                    self.block_entities.remove(&pos);
 
                     // check if there are any blocks left in the chunk with
                     // the local id of the block we just removed
-                    let mut has_block = false;
-
-                    for block_id in &self.blocks {
-                        // exit the loop if we've found another block with the current
-                        // block id, and set our boolean to true
-                        if *block_id == curr_block_id {
-                            has_block = true;
-                            break;
-                        }
-                    }
+                    let has_block = self.blocks.contains(&curr_block_id);
 
                     // clear the global block id if no
                     // blocks of its type exist inside the chunk anymore
                     if !has_block {
                         // id is local_id + 1: '0' is reserved for air
-                        self.ids[(curr_block_id + 1) as usize] = None;
+                        self.ids[(curr_block_id - 1) as usize] = None;
                     }
                 }
             }
@@ -138,6 +131,59 @@ impl Chunk {
         self.add_block(x, y, z, None)
     }
 
+    /// Places the block state `block` at `(x, y, z)`,
+    /// or removes the block there if `block` is `None`.<br>
+    /// Creates the block entity of the placed block, and drops the one of the block it replaces.<br>
+    /// Changing the state of a block keeps its block entity
+    pub fn place_block(
+        &mut self,
+        block_registry: &BlockRegistry,
+        state_registry: &BlockStateRegistry,
+        x: usize, y: usize, z: usize,
+        block: Option<StateId>
+    ) -> bool {
+        let pos = pos_as_index(x, y, z);
+
+        let old_block = self.get_block(state_registry, x, y, z).map(|state| state.get_block());
+        let new_block = block.and_then(|state_id| state_registry.get(state_id)).map(|state| state.get_block());
+
+        if old_block != new_block {
+            self.block_entities.remove(&pos);
+        }
+
+        if !self.add_block(x, y, z, block) {
+            return false;
+        }
+
+        let kind = new_block
+            .and_then(|raw_id| block_registry.get_by_raw_id(raw_id))
+            .and_then(|block| block.get_block_entity_kind());
+
+        if let Some(kind) = kind {
+            self.block_entities.entry(pos).or_insert_with(|| BlockEntity::new(kind));
+        }
+
+        true
+    }
+
+    /// Returns the block entity of the block at `(x, y, z)`, if it has one
+    pub fn get_block_entity(&self, x: usize, y: usize, z: usize) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos_as_index(x, y, z))
+    }
+
+    pub fn get_block_entity_mut(&mut self, x: usize, y: usize, z: usize) -> Option<&mut BlockEntity> {
+        self.block_entities.get_mut(&pos_as_index(x, y, z))
+    }
+
+    /// Every block entity in this chunk, along with the position of its block
+    pub fn block_entities(&self) -> impl Iterator<Item = ([usize; 3], &BlockEntity)> {
+        self.block_entities.iter().map(|(index, block_entity)| (index_as_pos(*index), block_entity))
+    }
+
+    pub fn block_entities_mut(&mut self) -> impl Iterator<Item = ([usize; 3], &mut BlockEntity)> {
+        self.block_entities.iter_mut().map(|(index, block_entity)| (index_as_pos(*index), block_entity))
+    }
+
     pub fn get_local_block_id(&self, x: usize, y: usize, z: usize) -> u16 {
         let index = pos_as_index(x, y, z);
 
@@ -148,26 +194,60 @@ impl Chunk {
         }
     }
 
-    pub fn get_block(&self, x: usize, y: usize, z: usize) -> Option<Block> {
+    /// Returns the state ID of the block at `(x, y, z)`,
+    /// or `None` if it is air
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> Option<StateId> {
         let block_id = self.get_local_block_id(x, y, z);
-        
+
         if block_id > 0 {
-            if let Some(block_string_id) = &self.ids[block_id as usize - 1] {
-                registry::get_block_from_registry_by_string(&block_string_id)
-            } else {
-                None
-            }
+            self.ids[block_id as usize - 1]
         } else {
             None
         }
     }
 
+    pub fn get_block<'a>(&self, state_registry: &'a BlockStateRegistry, x: usize, y: usize, z: usize) -> Option<&'a BlockState> {
+        self.get_block_state(x, y, z)
+            .and_then(|state_id| state_registry.get(state_id))
+    }
+
+    /// Returns `true` if any block in this chunk is in the state `state_id`
+    pub fn contains_state(&self, state_id: StateId) -> bool {
+        self.ids.contains(&Some(state_id))
+    }
+
+    /// Returns `true` if the block at `(x, y, z)` is opaque and covers
+    /// its whole `block_face` side, hiding the face of the block next to it
+    pub fn has_full_face_at(
+        &self,
+        block_registry: &BlockRegistry,
+        state_registry: &BlockStateRegistry,
+        x: usize, y: usize, z: usize,
+        block_face: BlockFace
+    ) -> bool {
+        self.get_block(state_registry, x, y, z)
+            .filter(|state| state.has_full_face(block_face))
+            .and_then(|state| block_registry.get_by_raw_id(state.get_block()))
+            .is_some_and(|block| block.is_opaque())
+    }
+
     /// Returns `false` if the local block ID at `(x, y, z)` is 0 (air).
     /// Returns `true` otherwise.
     pub fn has_block_at(&self, x: usize, y: usize, z: usize) -> bool {
         self.get_local_block_id(x, y, z) > 0
     }
 
+    /// Sets the climate of every column, indexed by `z * CHUNK_SIZE + x`
+    pub fn set_climate_map(&mut self, climate: Vec<Climate>) {
+        if climate.len() == self.climate.len() {
+            self.climate = climate;
+        }
+    }
+
+    pub fn get_climate(&self, x: usize, z: usize) -> Climate {
+        self.climate.get(z * CHUNK_SIZE + x).copied().unwrap_or_default()
+    }
+
     pub fn local_to_world_pos(&self, x: usize, y: usize, z: usize) -> Vec3 {
         Vec3::new(
             self.chunk_pos.x as f32 * CHUNK_SIZE as f32 + x as f32,
@@ -177,6 +257,120 @@ impl Chunk {
     }
 }
 
+/// A block state stored by its block's ID and property values
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SavedBlockState {
+    pub block: Identifier,
+
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// A chunk in a form that can be written to disk.<br>
+/// Blocks are stored by ID and property values instead of by state ID,
+/// since state IDs can change whenever the data packs do
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SavedChunk {
+    pub position: [f32; 3],
+
+    /// Every block state used in the chunk
+    pub palette: Vec<SavedBlockState>,
+
+    /// Index into `palette` + 1 of every block, 0 being air
+    pub blocks: Vec<u16>,
+
+    /// Block entities, along with the position of their block
+    #[serde(default)]
+    pub block_entities: Vec<([usize; 3], BlockEntity)>,
+}
+
+impl Chunk {
+    /// Returns this chunk in a form that can be written to disk
+    pub fn save(&self, block_registry: &BlockRegistry, state_registry: &BlockStateRegistry) -> SavedChunk {
+        let mut palette = Vec::new();
+        let mut local_ids = HashMap::new();
+
+        let blocks = self.blocks.iter().enumerate().map(|(index, _)| {
+            let [x, y, z] = index_as_pos(index);
+
+            let saved_state = self.get_block(state_registry, x, y, z).and_then(|state| {
+                let block = block_registry.get_by_raw_id(state.get_block())?;
+
+                Some(SavedBlockState {
+                    block: block.get_identifier(),
+                    properties: state.get_values().clone(),
+                })
+            });
+
+            match saved_state {
+                Some(saved_state) => *local_ids.entry(saved_state.clone()).or_insert_with(|| {
+                    palette.push(saved_state);
+                    palette.len() as u16
+                }),
+                None => 0
+            }
+        }).collect();
+
+        SavedChunk {
+            position: self.chunk_pos.to_array(),
+            palette,
+            blocks,
+            block_entities: self.block_entities()
+                .map(|(pos, block_entity)| (pos, block_entity.clone()))
+                .collect(),
+        }
+    }
+
+    /// Rebuilds a saved chunk.<br>
+    /// Blocks that don't exist anymore are replaced with air, and unknown property
+    /// values use the block's default state. Block entities are only kept
+    /// if their block still has the same kind of block entity
+    pub fn load(saved: &SavedChunk, block_registry: &BlockRegistry, state_registry: &BlockStateRegistry) -> Self {
+        let mut chunk = Chunk::new(Vec3::from(saved.position));
+
+        let states = saved.palette.iter().map(|saved_state| {
+            match block_registry.get(&saved_state.block) {
+                Some(block) => {
+                    let values = saved_state.properties.iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str()))
+                        .collect::<Vec<(&str, &str)>>();
+
+                    Some(block.get_state_id(&values).unwrap_or_else(|| block.get_default_state()))
+                },
+                None => {
+                    println!("[Warning] Saved chunk contains unknown block `{}`; replacing it with air", saved_state.block);
+                    None
+                }
+            }
+        }).collect::<Vec<Option<StateId>>>();
+
+        for (index, local_id) in saved.blocks.iter().enumerate().take(CHUNK_SIZE.pow(3)) {
+            let state_id = match local_id {
+                0 => None,
+                local_id => states.get(*local_id as usize - 1).copied().flatten()
+            };
+
+            if state_id.is_some() {
+                let [x, y, z] = index_as_pos(index);
+
+                chunk.place_block(block_registry, state_registry, x, y, z, state_id);
+            }
+        }
+
+        for ([x, y, z], block_entity) in &saved.block_entities {
+            let kind = chunk.get_block(state_registry, *x, *y, *z)
+                .and_then(|state| block_registry.get_by_raw_id(state.get_block()))
+                .and_then(|block| block.get_block_entity_kind());
+
+            if kind.is_some_and(|kind| block_entity.is_kind(kind)) {
+                chunk.block_entities.insert(pos_as_index(*x, *y, *z), block_entity.clone());
+            }
+        }
+
+        chunk
+    }
+}
+
 pub fn pos_as_index(local_x: usize, local_y: usize, local_z: usize) -> usize {
     //local_x + local_y * CHUNK_SIZE + local_z * CHUNK_SIZE * CHUNK_SIZE
     local_x | local_y << BLOCK_Y_SHIFT | local_z << BLOCK_Z_SHIFT
@@ -190,10 +384,16 @@ pub fn index_as_pos(index: usize) -> [usize; 3] {
     [block_x, block_y, block_z]
 }
 
-pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
+pub fn build_chunk_mesh(
+    chunk: &Chunk,
+    block_registry: &BlockRegistry,
+    state_registry: &BlockStateRegistry,
+    colormaps: &Colormaps
+) -> Mesh {
     let mut positions: Vec<[f32; 3]> = Vec::new();
     let mut normals: Vec<[f32; 3]> = Vec::new();
     let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
     let mut indices: Vec<u32> = Vec::new();
 
     for z in 0..CHUNK_SIZE {
@@ -209,12 +409,57 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                 let is_block = chunk.blocks[index] > 0;
 
                 if is_block {
-                    let cull_code = cull_neighbors(&chunk, x, y, z);
+                    let cull_code = cull_neighbors(chunk, block_registry, state_registry, x, y, z);
 
                     let block_pos = chunk.local_to_world_pos(x, y, z);
+                    let climate = chunk.get_climate(x, z);
+
+                    if let Some(state_id) = chunk.ids[chunk.blocks[index] as usize - 1] {
+                        if let Some(block) = state_registry.get(state_id) {
+                            // models only hide faces that can be culled from the side they're on
+                            if let Some(quads) = block.get_model() {
+                                for quad in quads {
+                                    if quad.cull.is_some_and(|side| cull_code & side.cull_code() == 0) {
+                                        continue;
+                                    }
+
+                                    build_face(
+                                        &mut positions,
+                                        &mut normals,
+                                        &mut uvs,
+                                        &mut indices,
+                                        &quad.vertices,
+                                        &mut quad.uvs.to_vec(),
+                                        &block_pos,
+                                    );
+
+                                    colors.resize(positions.len(), block.get_tint_color(quad.face, colormaps, climate));
+                                }
+
+                                continue;
+                            }
+
+                            let [world_x, world_y, world_z] = block_pos.to_array().map(|axis| axis.round() as i32);
+
+                            // connected faces pick the variant matching the same block around them,
+                            // random faces pick theirs by their position
+                            let get_uvs = |block_face: BlockFace| match block_face {
+                                _ if block.is_connected(block_face) => block.get_uvs_connected(
+                                    block_face,
+                                    connect_neighbors(chunk, state_registry, x, y, z, block_face)
+                                ),
+                                _ if block.is_random(block_face) => block.get_uvs_random(
+                                    block_face,
+                                    position_seed(world_x, world_y, world_z, block_face)
+                                ),
+                                BlockFace::Top => block.get_uvs_top(),
+                                BlockFace::Bottom => block.get_uvs_bottom(),
+                                BlockFace::Left => block.get_uvs_left(),
+                                BlockFace::Right => block.get_uvs_right(),
+                                BlockFace::Front => block.get_uvs_front(),
+                                BlockFace::Back => block.get_uvs_back(),
+                            };
 
-                    if let Some(block_id) = &chunk.ids[chunk.blocks[index] as usize - 1] {
-                        if let Some(block) = registry::get_block_from_registry(&Identifier::from(block_id).unwrap()) {
                             if (cull_code & (VoxelCullCode::U as u8)) == VoxelCullCode::U as u8 {
                                 build_face(
                                     &mut positions,
@@ -222,9 +467,11 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_TOP,
-                                    &mut block.get_uvs_top(),
+                                    &mut get_uvs(BlockFace::Top),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Top, colormaps, climate));
                             }
     
                             if (cull_code & (VoxelCullCode::D as u8)) == VoxelCullCode::D as u8 {
@@ -234,9 +481,11 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_BOTTOM,
-                                    &mut block.get_uvs_bottom(),
+                                    &mut get_uvs(BlockFace::Bottom),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Bottom, colormaps, climate));
                             }
     
                             if (cull_code & (VoxelCullCode::R as u8)) == VoxelCullCode::R as u8 {
@@ -246,9 +495,11 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_RIGHT,
-                                    &mut block.get_uvs_right(),
+                                    &mut get_uvs(BlockFace::Right),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Right, colormaps, climate));
                             }
     
                             if (cull_code & (VoxelCullCode::L as u8)) == VoxelCullCode::L as u8 {
@@ -258,9 +509,11 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_LEFT,
-                                    &mut block.get_uvs_left(),
+                                    &mut get_uvs(BlockFace::Left),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Left, colormaps, climate));
                             }
     
                             if (cull_code & (VoxelCullCode::F as u8)) == VoxelCullCode::F as u8 {
@@ -270,9 +523,11 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_FRONT,
-                                    &mut block.get_uvs_front(),
+                                    &mut get_uvs(BlockFace::Front),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Front, colormaps, climate));
                             }
     
                             if (cull_code & (VoxelCullCode::B as u8)) == VoxelCullCode::B as u8 {
@@ -282,9 +537,32 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
                                     &mut uvs,
                                     &mut indices,
                                     VERTICES_BACK,
-                                    &mut block.get_uvs_back(),
+                                    &mut get_uvs(BlockFace::Back),
                                     &block_pos,
                                 );
+
+                                colors.resize(positions.len(), block.get_tint_color(BlockFace::Back, colormaps, climate));
+                            }
+
+                            // overlays go on top of every face that was drawn
+                            for block_face in BlockFace::ALL {
+                                if cull_code & block_face.cull_code() == 0 {
+                                    continue;
+                                }
+
+                                for (layer_index, layer) in block.get_layers(block_face).iter().enumerate() {
+                                    build_face(
+                                        &mut positions,
+                                        &mut normals,
+                                        &mut uvs,
+                                        &mut indices,
+                                        &block_face.get_layer_vertices(layer_index),
+                                        &mut get_face_uvs(block_face, &layer.tex_coords).to_vec(),
+                                        &block_pos,
+                                    );
+
+                                    colors.resize(positions.len(), block.get_layer_color(layer, colormaps, climate));
+                                }
                             }
                         }
                     }
@@ -302,6 +580,7 @@ pub fn build_chunk_mesh(chunk: &Chunk) -> Mesh {
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
 
     mesh
 }
@@ -344,4 +623,112 @@ fn build_face(
     for f_index in &block_indicies {
         indicies.push(f_index.clone() + index);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::sprite::Rect;
+    use ron::extensions::Extensions;
+
+    use super::*;
+
+    use crate::{
+        block_entity::{BlockEntityKind, StoredItem},
+        registry::{BlockTextureCoords, ItemRegistry, RegistryLoadReport, register_block},
+    };
+
+    const LEVER: &str = r#"(
+        id: "test:lever",
+        has_item: false,
+        properties: { "powered": Bool },
+    )"#;
+
+    const CHEST: &str = r#"(
+        id: "test:chest",
+        has_item: false,
+        block_entity: Container(slots: 3),
+    )"#;
+
+    const FURNACE_CHEST: &str = r#"(
+        id: "test:chest",
+        has_item: false,
+        block_entity: Furnace,
+    )"#;
+
+    fn registries(block_defs: &[&str]) -> (BlockRegistry, BlockStateRegistry) {
+        let mut block_registry = BlockRegistry::default();
+        let mut state_registry = BlockStateRegistry::default();
+        let mut item_registry = ItemRegistry::default();
+        let mut report = RegistryLoadReport::default();
+        let tex_coords = BlockTextureCoords::new(Rect { min: Vec2::ZERO, max: Vec2::ONE });
+
+        for block_def in block_defs {
+            let block_def = ron::Options::default()
+                .with_default_extension(Extensions::IMPLICIT_SOME)
+                .from_str::<BlockDefinition>(block_def)
+                .unwrap();
+
+            register_block(&mut block_registry, &mut state_registry, &mut item_registry, &tex_coords, &HashMap::new(), &mut report, "test", &block_def);
+        }
+
+        (block_registry, state_registry)
+    }
+
+    fn state_id(block_registry: &BlockRegistry, name: &str, values: &[(&str, &str)]) -> StateId {
+        block_registry.get(&Identifier::new("test", name))
+            .and_then(|block| block.get_state_id(values))
+            .unwrap()
+    }
+
+    /// A chunk with a powered lever and a chest holding some stone
+    fn build_chunk(block_registry: &BlockRegistry, state_registry: &BlockStateRegistry) -> Chunk {
+        let mut chunk = Chunk::new(Vec3::new(1., 0., -2.));
+
+        let powered_lever = state_id(block_registry, "lever", &[("powered", "true")]);
+        let chest = state_id(block_registry, "chest", &[]);
+
+        chunk.place_block(block_registry, state_registry, 1, 2, 3, Some(powered_lever));
+        chunk.place_block(block_registry, state_registry, 4, 5, 6, Some(chest));
+
+        if let Some(BlockEntity::Container { slots }) = chunk.get_block_entity_mut(4, 5, 6) {
+            slots[0] = Some(StoredItem { id: String::from("blocky:stone"), count: 3 });
+        }
+
+        chunk
+    }
+
+    #[test]
+    fn saved_chunks_load_back() {
+        let (block_registry, state_registry) = registries(&[LEVER, CHEST]);
+        let chunk = build_chunk(&block_registry, &state_registry);
+
+        let saved = ron::to_string(&chunk.save(&block_registry, &state_registry)).unwrap();
+        let saved = ron::from_str::<SavedChunk>(&saved).unwrap();
+        let loaded = Chunk::load(&saved, &block_registry, &state_registry);
+
+        assert_eq!(loaded.get_chunk_pos(), chunk.get_chunk_pos());
+
+        for index in 0..CHUNK_SIZE.pow(3) {
+            let [x, y, z] = index_as_pos(index);
+
+            assert_eq!(loaded.get_block_state(x, y, z), chunk.get_block_state(x, y, z));
+        }
+
+        assert_eq!(loaded.block_entities().count(), 1);
+        assert_eq!(loaded.get_block_entity(4, 5, 6), chunk.get_block_entity(4, 5, 6));
+    }
+
+    #[test]
+    fn block_entities_of_another_kind_are_dropped() {
+        let (block_registry, state_registry) = registries(&[LEVER, CHEST]);
+        let saved = build_chunk(&block_registry, &state_registry).save(&block_registry, &state_registry);
+
+        // the chest is a furnace now, and the lever is gone
+        let (block_registry, state_registry) = registries(&[FURNACE_CHEST]);
+        let loaded = Chunk::load(&saved, &block_registry, &state_registry);
+
+        assert!(!loaded.has_block_at(1, 2, 3));
+        assert_eq!(loaded.get_block_state(4, 5, 6), Some(state_id(&block_registry, "chest", &[])));
+        assert_eq!(loaded.get_block_entity(4, 5, 6), Some(&BlockEntity::new(BlockEntityKind::Furnace)));
+    }
+}
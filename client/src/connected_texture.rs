@@ -0,0 +1,126 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// Extension of the tile sheets that connected textures are built from.<br>
+/// `textures/block/glass.ctm.png` is the connected version of the texture `glass`
+pub const CONNECTED_EXTENSION: &str = "ctm.png";
+
+/// Number of tiles in a connected texture sheet, from left to right:
+/// no connections, connected on every side, connected left and right,
+/// connected up and down, and connected on every side but the corners
+pub const CONNECTED_SHEET_TILES: u32 = 5;
+
+const TILE_ISOLATED: u32 = 0;
+const TILE_CENTER: u32 = 1;
+const TILE_HORIZONTAL: u32 = 2;
+const TILE_VERTICAL: u32 = 3;
+const TILE_INNER_CORNERS: u32 = 4;
+
+// neighbours of a face, as seen on its texture
+pub const CONNECT_UP: u8 = 1;
+pub const CONNECT_RIGHT: u8 = 2;
+pub const CONNECT_DOWN: u8 = 4;
+pub const CONNECT_LEFT: u8 = 8;
+pub const CONNECT_UP_RIGHT: u8 = 16;
+pub const CONNECT_DOWN_RIGHT: u8 = 32;
+pub const CONNECT_DOWN_LEFT: u8 = 64;
+pub const CONNECT_UP_LEFT: u8 = 128;
+
+/// Every combination of neighbours that looks different, in atlas order.<br>
+/// Corners only count when both sides next to them are connected too,
+/// which leaves 47 of the 256 combinations
+pub const CONNECTED_VARIANTS: [u8; 47] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    19, 23, 27, 31, 38, 39, 46, 47, 55, 63, 76, 77, 78, 79, 95,
+    110, 111, 127, 137, 139, 141, 143, 155, 159, 175, 191,
+    205, 207, 223, 239, 255,
+];
+
+/// Removes the corners from `connections` that don't change how the face looks
+pub fn simplify_connections(connections: u8) -> u8 {
+    let corners = [
+        (CONNECT_UP_RIGHT, CONNECT_UP | CONNECT_RIGHT),
+        (CONNECT_DOWN_RIGHT, CONNECT_DOWN | CONNECT_RIGHT),
+        (CONNECT_DOWN_LEFT, CONNECT_DOWN | CONNECT_LEFT),
+        (CONNECT_UP_LEFT, CONNECT_UP | CONNECT_LEFT),
+    ];
+
+    corners.into_iter().fold(connections, |connections, (corner, sides)| {
+        if connections & sides == sides {
+            connections
+        } else {
+            connections & !corner
+        }
+    })
+}
+
+/// Returns the index into `CONNECTED_VARIANTS` of the variant to draw for `connections`
+pub fn get_variant_index(connections: u8) -> usize {
+    CONNECTED_VARIANTS.binary_search(&simplify_connections(connections)).unwrap_or(0)
+}
+
+/// Builds every variant in `CONNECTED_VARIANTS` out of the tiles of `sheet`.<br>
+/// Each quarter of a variant is copied from the tile matching
+/// the neighbours on the two sides and the corner it touches
+pub fn build_connected_variants(sheet: &Image) -> Result<Vec<Image>, String> {
+    let sheet = if sheet.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb {
+        sheet.clone()
+    } else {
+        sheet.convert(TextureFormat::Rgba8UnormSrgb)
+            .ok_or_else(|| String::from("its format can't be converted to RGBA"))?
+    };
+
+    let size = sheet.texture_descriptor.size;
+    let tile_size = size.height;
+
+    if tile_size == 0 || size.width != tile_size * CONNECTED_SHEET_TILES {
+        return Err(format!(
+            "it is {}x{} pixels, which isn't a row of {CONNECTED_SHEET_TILES} square tiles",
+            size.width, size.height
+        ));
+    }
+
+    let half_size = tile_size / 2;
+
+    let variants = CONNECTED_VARIANTS.iter().map(|connections| {
+        let mut data = Vec::with_capacity((tile_size * tile_size * 4) as usize);
+
+        for y in 0..tile_size {
+            for x in 0..tile_size {
+                let (vertical, horizontal, corner) = match (x < half_size, y < half_size) {
+                    (true, true) => (CONNECT_UP, CONNECT_LEFT, CONNECT_UP_LEFT),
+                    (false, true) => (CONNECT_UP, CONNECT_RIGHT, CONNECT_UP_RIGHT),
+                    (true, false) => (CONNECT_DOWN, CONNECT_LEFT, CONNECT_DOWN_LEFT),
+                    (false, false) => (CONNECT_DOWN, CONNECT_RIGHT, CONNECT_DOWN_RIGHT),
+                };
+
+                let tile = match (connections & vertical != 0, connections & horizontal != 0) {
+                    (false, false) => TILE_ISOLATED,
+                    (false, true) => TILE_HORIZONTAL,
+                    (true, false) => TILE_VERTICAL,
+                    (true, true) if connections & corner != 0 => TILE_CENTER,
+                    (true, true) => TILE_INNER_CORNERS,
+                };
+
+                let start = ((y * size.width + tile * tile_size + x) * 4) as usize;
+
+                data.extend_from_slice(&sheet.data[start..start + 4]);
+            }
+        }
+
+        Image::new(
+            Extent3d {
+                width: tile_size,
+                height: tile_size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }).collect();
+
+    Ok(variants)
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use bevy::{asset::{AssetLoader, LoadContext, LoadedAsset, BoxedFuture}, prelude::{FromWorld, World}};
+use ron::extensions::Extensions;
+
+use crate::{block::BlockDefinition, item::ItemDefinitionSeed, item_trait::ItemTraitTypes, tags::TagDefinition, model::BlockModelDefinition, BlockyPathError};
+
+/// The kind of definition a data file holds, decided
+/// by the folder it is in (`data/<namespace>/<kind>/...`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Block,
+    Item,
+    BlockTag,
+    ItemTag,
+    Model,
+}
+
+impl DefinitionKind {
+    /// Name of the folder definitions of this kind are stored in
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            DefinitionKind::Block => "blocks",
+            DefinitionKind::Item => "items",
+            DefinitionKind::BlockTag => "tags/blocks",
+            DefinitionKind::ItemTag => "tags/items",
+            DefinitionKind::Model => "models",
+        }
+    }
+
+    /// Returns the kind of definition stored at `path`,
+    /// relative to the `assets` folder
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let mut components = path.components().map(|component| component.as_os_str());
+
+        if components.next()? != "data" {
+            return None;
+        }
+
+        // skip the namespace folder
+        components.next()?;
+
+        match components.next()?.to_str()? {
+            "blocks" => Some(DefinitionKind::Block),
+            "items" => Some(DefinitionKind::Item),
+            "models" => Some(DefinitionKind::Model),
+            "tags" => match components.next()?.to_str()? {
+                "blocks" => Some(DefinitionKind::BlockTag),
+                "items" => Some(DefinitionKind::ItemTag),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns the namespace folder a data file at `path` is in
+pub fn namespace_from_path(path: &Path) -> Option<String> {
+    let mut components = path.components().map(|component| component.as_os_str());
+
+    if components.next()? != "data" {
+        return None;
+    }
+
+    components.next().map(|namespace| namespace.to_string_lossy().into_owned())
+}
+
+/// Loads `.ron` files inside a data pack as `BlockDefinition`,
+/// `ItemDefinition`, `TagDefinition` or `BlockModelDefinition` assets
+pub struct DefinitionLoader {
+    /// Shared with the `ItemTraitTypes` resource, so traits registered later are still read
+    item_trait_types: ItemTraitTypes,
+}
+
+impl FromWorld for DefinitionLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            item_trait_types: world.get_resource_or_insert_with(ItemTraitTypes::with_builtins).clone(),
+        }
+    }
+}
+
+impl AssetLoader for DefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().to_string_lossy().into_owned();
+
+            match DefinitionKind::from_path(load_context.path()) {
+                Some(DefinitionKind::Block) => {
+                    // optional block fields can be written without `Some(...)`
+                    let block_def = ron::Options::default()
+                        .with_default_extension(Extensions::IMPLICIT_SOME)
+                        .from_bytes::<BlockDefinition>(bytes)
+                        .map_err(|err| BlockyPathError::FileParseError(path, err))?;
+
+                    load_context.set_default_asset(LoadedAsset::new(block_def));
+                },
+                Some(DefinitionKind::Item) => {
+                    // item traits are written like enum variants, but read as the registered trait types
+                    let item_def = ron::Options::default()
+                        .with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES)
+                        .from_bytes_seed(bytes, ItemDefinitionSeed(&self.item_trait_types))
+                        .map_err(|err| BlockyPathError::FileParseError(path, err))?;
+
+                    load_context.set_default_asset(LoadedAsset::new(item_def));
+                },
+                Some(DefinitionKind::BlockTag | DefinitionKind::ItemTag) => {
+                    let tag_def = ron::de::from_bytes::<TagDefinition>(bytes).map_err(|err|
+                        BlockyPathError::FileParseError(path, err)
+                    )?;
+
+                    load_context.set_default_asset(LoadedAsset::new(tag_def));
+                },
+                Some(DefinitionKind::Model) => {
+                    let model_def = ron::Options::default()
+                        .with_default_extension(Extensions::IMPLICIT_SOME)
+                        .from_bytes::<BlockModelDefinition>(bytes)
+                        .map_err(|err| BlockyPathError::FileParseError(path, err))?;
+
+                    load_context.set_default_asset(LoadedAsset::new(model_def));
+                },
+                None => return Err(BlockyPathError::UnknownDataFile(path).into())
+            }
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
@@ -1,22 +1,38 @@
-use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::RwLock};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Namespace of IDs written without one: `grass_block` is `blocky:grass_block`
+pub const DEFAULT_NAMESPACE: &str = "blocky";
 
 /// Returned when an Identifier fails during validation
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum IdValidationError {
-    #[error("ID `{0}` is missing a colon: expected format `namespace:value` (example: `blocky:grass_block`")]
-    MissingColon(String),
-    
     #[error("ID `{0}` is invalid and contains too many colons: expected format `namespace:value` (example: `blocky:grass_block`")]
     TooManyColons(String),
-    
-    #[error("ID `{id:?}` contains invalid characters: {invalid_chars:?}")]
+
+    #[error("ID `{0}` has an empty namespace or name: expected format `namespace:value` (example: `blocky:grass_block`")]
+    EmptyPart(String),
+
+    #[error("ID `{id}` contains invalid characters: {}", format_invalid_chars(.invalid_chars))]
     InvalidCharacters {
         id: String,
-        invalid_chars: String
+        /// Every invalid character, along with its position in `id`
+        invalid_chars: Vec<(usize, char)>
     },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+fn format_invalid_chars(invalid_chars: &[(usize, char)]) -> String {
+    invalid_chars.iter()
+        .map(|(position, invalid_char)| format!("{invalid_char:?} at position {position}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// A namespaced ID like `blocky:grass_block`.<br>
+/// Names can be split into folders with `/`, like `blocky:ores/iron`.<br>
+/// Serialized as the string `namespace:name`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Identifier {
     namespace: String,
     name: String
@@ -30,53 +46,206 @@ impl Identifier {
         }
     }
 
-    pub fn from(id: &String) -> Result<Self, IdValidationError> {
-        Self::from_str(&id)
+    /// Returns the interned handle for this ID
+    pub fn intern(&self) -> InternedId {
+        InternedId::new(self)
+    }
+
+    pub fn get_namespace(&self) -> String { self.namespace.clone() }
+    
+    pub fn get_name(&self) -> String { self.name.clone() }
+
+    /// Checks that the namespace and name only use lowercase letters, digits, `_`, `-` and `.`.<br>
+    /// The name can also use `/` between folders, but not at
+    /// its start or end, or twice in a row
+    pub fn validate(&self) -> Result<(), IdValidationError> {
+        // positions are counted in the full `namespace:name` form
+        validate_parts(&self.to_string(), &self.namespace, &self.name, self.namespace.chars().count() + 1)
     }
+}
+
+/// Validates the namespace and name of the ID written as `id`.<br>
+/// `name_start` is where the name starts in `id`, so positions point at what was written
+fn validate_parts(id: &str, namespace: &str, name: &str, name_start: usize) -> Result<(), IdValidationError> {
+    if namespace.is_empty() || name.is_empty() {
+        return Err(IdValidationError::EmptyPart(String::from(id)));
+    }
+
+    let is_valid = |c: char| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.');
+
+    let name_len = name.chars().count();
+    let name_chars = name.chars().collect::<Vec<char>>();
 
-    pub fn from_str(id: &str) -> Result<Self, IdValidationError> {
-        let splits: Vec<&str> = id.split(":").collect();
+    let invalid_namespace = namespace.chars().enumerate()
+        .filter(|(_, c)| !is_valid(*c));
 
-        // there should only be 2 strings; the id's namespace, and the name of the object
-        if splits.len() != 2 {
-            if splits.len() == 1 {
-                Err(IdValidationError::MissingColon(String::from(id)))
-            } else {
-                Err(IdValidationError::TooManyColons(String::from(id)))
-            }
-        } else {
-            let id = Self::new(splits[0], splits[1]);
+    let invalid_name = name_chars.iter().copied().enumerate()
+        .filter(|(index, c)| match c {
+            '/' => *index == 0 || *index == name_len - 1 || name_chars[index - 1] == '/',
+            c => !is_valid(*c)
+        })
+        .map(|(index, c)| (name_start + index, c));
 
-            id.validate()?;
+    let invalid_chars = invalid_namespace.chain(invalid_name).collect::<Vec<(usize, char)>>();
 
-            Ok(id)
+    if invalid_chars.is_empty() {
+        Ok(())
+    } else {
+        Err(IdValidationError::InvalidCharacters {
+            id: String::from(id),
+            invalid_chars
+        })
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = IdValidationError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        let splits: Vec<&str> = id.split(':').collect();
+
+        // there should be at most 2 strings; the id's namespace, and the name of the object.
+        // a bare name is checked before the default namespace is added,
+        // so positions match what was written
+        match splits[..] {
+            [name] => {
+                validate_parts(id, DEFAULT_NAMESPACE, name, 0)?;
+
+                Ok(Self::new(DEFAULT_NAMESPACE, name))
+            },
+            [namespace, name] => {
+                validate_parts(id, namespace, name, namespace.chars().count() + 1)?;
+
+                Ok(Self::new(namespace, name))
+            },
+            _ => Err(IdValidationError::TooManyColons(String::from(id)))
         }
     }
+}
 
-    pub fn get_namespace(&self) -> String { self.namespace.clone() }
-    
-    pub fn get_name(&self) -> String { self.name.clone() }
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.name)
+    }
+}
 
-    pub fn as_string(&self) -> String { format!("{}:{}", self.namespace, self.name) }
+impl Serialize for Identifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
 
-    pub fn validate(&self) -> Result<(), IdValidationError> {
-        let valid_chars = "abcdefghijklmnopqrstuvwxyz0123456789_-.";
+impl<'de> Deserialize<'de> for Identifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
 
-        let invalid_chars_namespace = self.namespace.chars().filter(|x| {
-            !valid_chars.chars().collect::<Vec<char>>().contains(x)
-        }).collect::<String>();
+/// A `Copy` handle to an interned `Identifier`.<br>
+/// Comparing and hashing handles only touches a number, and every
+/// handle to the same ID is equal, so they're cheap to use as keys.<br>
+/// Handles are ordered by when their ID was first interned, not alphabetically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedId(u32);
 
-        let invalid_chars_name = self.name.chars().filter(|x| {
-            !valid_chars.chars().collect::<Vec<char>>().contains(x)
-        }).collect::<String>();
-    
-        if invalid_chars_namespace.len() > 0 || invalid_chars_name.len() > 0 {
-            Err(IdValidationError::InvalidCharacters {
-                id: self.as_string(),
-                invalid_chars: invalid_chars_namespace + &invalid_chars_name
-            })
-        } else {
-            Ok(())
+/// Every interned ID. IDs are never removed,
+/// so they're leaked to hand out `'static` references
+struct Interner {
+    ids: Vec<&'static Identifier>,
+    handles: BTreeMap<&'static Identifier, InternedId>,
+}
+
+static INTERNER: RwLock<Interner> = RwLock::new(Interner {
+    ids: Vec::new(),
+    handles: BTreeMap::new(),
+});
+
+impl InternedId {
+    /// Returns the handle for `id`, interning it if this is the first time it's seen
+    pub fn new(id: &Identifier) -> Self {
+        if let Some(handle) = INTERNER.read().unwrap().handles.get(id) {
+            return *handle;
+        }
+
+        let mut interner = INTERNER.write().unwrap();
+
+        // another thread may have interned it while we waited for the lock
+        if let Some(handle) = interner.handles.get(id) {
+            return *handle;
         }
+
+        let handle = InternedId(interner.ids.len() as u32);
+        let id: &'static Identifier = Box::leak(Box::new(id.clone()));
+
+        interner.ids.push(id);
+        interner.handles.insert(id, handle);
+
+        handle
+    }
+
+    /// Returns the handle for `id` if it was interned before, without interning it
+    pub fn find(id: &Identifier) -> Option<Self> {
+        INTERNER.read().unwrap().handles.get(id).copied()
+    }
+
+    /// Returns the ID this handle stands for
+    pub fn get(self) -> &'static Identifier {
+        INTERNER.read().unwrap().ids[self.0 as usize]
+    }
+}
+
+impl From<&Identifier> for InternedId {
+    fn from(id: &Identifier) -> Self {
+        InternedId::new(id)
+    }
+}
+
+impl FromStr for InternedId {
+    type Err = IdValidationError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Ok(InternedId::new(&id.parse()?))
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for InternedId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl Serialize for InternedId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Identifier::deserialize(deserializer).map(|id| InternedId::new(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_chars(id: &str) -> (String, Vec<(usize, char)>) {
+        match Identifier::from_str(id) {
+            Err(IdValidationError::InvalidCharacters { id, invalid_chars }) => (id, invalid_chars),
+            result => panic!("expected invalid characters, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn bare_ids_report_positions_in_the_input() {
+        assert_eq!(invalid_chars("Grass"), (String::from("Grass"), vec![(0, 'G')]));
+        assert_eq!(invalid_chars("ores//Iron"), (String::from("ores//Iron"), vec![(5, '/'), (6, 'I')]));
+    }
+
+    #[test]
+    fn namespaced_ids_report_positions_in_the_input() {
+        assert_eq!(invalid_chars("Blocky:grass!"), (String::from("Blocky:grass!"), vec![(0, 'B'), (12, '!')]));
+        assert_eq!(Identifier::from_str("grass").unwrap(), Identifier::new(DEFAULT_NAMESPACE, "grass"));
+    }
+}
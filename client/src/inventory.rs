@@ -0,0 +1,616 @@
+use bevy::{prelude::*, input::mouse::MouseWheel, sprite::Rect};
+use bevy_egui::{EguiContext, egui};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    AppState,
+    item::{ItemDefinition, ItemStack},
+    item_trait::Armor,
+    player_cam::PlayerCamera,
+    registry::{ItemRegistry, ItemTextureCoords},
+    texture_atlas::TextureAtlasHandles,
+};
+
+pub const HOTBAR_SLOTS: usize = 9;
+pub const MAIN_SLOTS: usize = 27;
+pub const ARMOR_SLOTS: usize = 4;
+
+/// Size in points of a slot drawn on screen
+const SLOT_SIZE: f32 = 40.;
+
+/// Keys that select a hotbar slot, in slot order
+const HOTBAR_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+/// A single slot of an `Inventory`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventorySlot {
+    Hotbar(usize),
+    Main(usize),
+    Armor(usize),
+    Offhand,
+}
+
+/// Items carried by a player
+#[derive(Component, Debug, Clone)]
+pub struct Inventory {
+    pub hotbar: Vec<Option<ItemStack>>,
+    pub main: Vec<Option<ItemStack>>,
+    pub armor: Vec<Option<ItemStack>>,
+    pub offhand: Option<ItemStack>,
+
+    /// Index of the hotbar slot being held
+    pub selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            hotbar: vec![None; HOTBAR_SLOTS],
+            main: vec![None; MAIN_SLOTS],
+            armor: vec![None; ARMOR_SLOTS],
+            offhand: None,
+            selected: 0,
+        }
+    }
+}
+
+impl Inventory {
+    fn slot(&self, slot: InventorySlot) -> Option<&Option<ItemStack>> {
+        match slot {
+            InventorySlot::Hotbar(index) => self.hotbar.get(index),
+            InventorySlot::Main(index) => self.main.get(index),
+            InventorySlot::Armor(index) => self.armor.get(index),
+            InventorySlot::Offhand => Some(&self.offhand),
+        }
+    }
+
+    fn slot_mut(&mut self, slot: InventorySlot) -> Option<&mut Option<ItemStack>> {
+        match slot {
+            InventorySlot::Hotbar(index) => self.hotbar.get_mut(index),
+            InventorySlot::Main(index) => self.main.get_mut(index),
+            InventorySlot::Armor(index) => self.armor.get_mut(index),
+            InventorySlot::Offhand => Some(&mut self.offhand),
+        }
+    }
+
+    pub fn get(&self, slot: InventorySlot) -> Option<&ItemStack> {
+        self.slot(slot).and_then(|stack| stack.as_ref())
+    }
+
+    pub fn get_mut(&mut self, slot: InventorySlot) -> Option<&mut ItemStack> {
+        self.slot_mut(slot).and_then(|stack| stack.as_mut())
+    }
+
+    /// The stack in the selected hotbar slot
+    pub fn get_selected(&self) -> Option<&ItemStack> {
+        self.get(InventorySlot::Hotbar(self.selected))
+    }
+
+    pub fn get_selected_mut(&mut self) -> Option<&mut ItemStack> {
+        self.get_mut(InventorySlot::Hotbar(self.selected))
+    }
+
+    /// Moves the hotbar selection by `offset` slots, wrapping around at either end
+    pub fn scroll_selection(&mut self, offset: i32) {
+        let slots = self.hotbar.len() as i32;
+
+        if slots > 0 {
+            self.selected = (self.selected as i32 + offset).rem_euclid(slots) as usize;
+        }
+    }
+
+    /// Adds `stack` to the hotbar and then the main slots, filling stacks of
+    /// the same item before empty slots.<br>
+    /// Returns whatever didn't fit
+    pub fn insert(&mut self, stack: ItemStack, item_registry: &ItemRegistry) -> Option<ItemStack> {
+        let slots = (0..self.hotbar.len()).map(InventorySlot::Hotbar)
+            .chain((0..self.main.len()).map(InventorySlot::Main))
+            .collect::<Vec<_>>();
+
+        self.insert_into(&slots, stack, item_registry)
+    }
+
+    /// Adds `stack` to `slots`, in order, returning whatever didn't fit
+    fn insert_into(&mut self, slots: &[InventorySlot], stack: ItemStack, item_registry: &ItemRegistry) -> Option<ItemStack> {
+        let item_def = match item_registry.get(stack.item.get()) {
+            Some(item_def) => item_def,
+            None => {
+                println!("[Error] can't add unknown item \"{}\" to an inventory", stack.item);
+                return Some(stack);
+            }
+        };
+
+        let mut remainder = Some(stack);
+
+        let slots = slots.iter()
+            .copied()
+            .filter(|slot| accepts(*slot, item_def))
+            .collect::<Vec<_>>();
+
+        // top up existing stacks first
+        for slot in &slots {
+            if let Some(Some(target)) = self.slot_mut(*slot) {
+                remainder = remainder.and_then(|stack| target.merge(stack, item_def));
+            }
+        }
+
+        for slot in &slots {
+            if let (Some(target @ None), Some(stack)) = (self.slot_mut(*slot), remainder.as_mut()) {
+                *target = stack.split(item_def.get_stack_size());
+
+                if stack.is_empty() {
+                    remainder = None;
+                }
+            }
+        }
+
+        remainder
+    }
+
+    /// Takes up to `count` items out of `slot`
+    pub fn remove(&mut self, slot: InventorySlot, count: u32) -> Option<ItemStack> {
+        let target = self.slot_mut(slot)?;
+        let taken = target.as_mut()?.split(count);
+
+        if target.as_ref().is_some_and(|stack| stack.is_empty()) {
+            *target = None;
+        }
+
+        taken
+    }
+
+    /// Swaps the contents of two slots, unless either stack can't go in its new slot
+    pub fn swap(&mut self, a: InventorySlot, b: InventorySlot, item_registry: &ItemRegistry) {
+        if a == b || self.slot(a).is_none() || self.slot(b).is_none() {
+            return;
+        }
+
+        let fits = |stack: Option<&ItemStack>, slot| stack.is_none_or(|stack|
+            item_registry.get(stack.item.get()).is_some_and(|item_def| accepts(slot, item_def))
+        );
+
+        if !fits(self.get(a), b) || !fits(self.get(b), a) {
+            return;
+        }
+
+        let a_stack = self.slot_mut(a).and_then(Option::take);
+        let b_stack = self.slot_mut(b).and_then(Option::take);
+
+        if let Some(target) = self.slot_mut(a) {
+            *target = b_stack;
+        }
+
+        if let Some(target) = self.slot_mut(b) {
+            *target = a_stack;
+        }
+    }
+
+    /// Moves the stack in `slot` between the hotbar and the main slots,
+    /// putting armor on first if it has a free slot.<br>
+    /// Armor and the offhand are emptied into the hotbar and main slots.<br>
+    /// Anything that doesn't fit stays where it was
+    pub fn quick_move(&mut self, slot: InventorySlot, item_registry: &ItemRegistry) {
+        let stack = match self.slot_mut(slot).and_then(Option::take) {
+            Some(stack) => stack,
+            None => return
+        };
+
+        let hotbar = (0..self.hotbar.len()).map(InventorySlot::Hotbar);
+        let main = (0..self.main.len()).map(InventorySlot::Main);
+        let armor = (0..self.armor.len()).map(InventorySlot::Armor);
+
+        // slots that don't take the item are skipped by `insert_into`
+        let targets = match slot {
+            InventorySlot::Hotbar(_) => armor.chain(main).collect::<Vec<_>>(),
+            InventorySlot::Main(_) => armor.chain(hotbar).collect(),
+            InventorySlot::Armor(_) | InventorySlot::Offhand => hotbar.chain(main).collect(),
+        };
+
+        let remainder = self.insert_into(&targets, stack, item_registry);
+
+        if let Some(target) = self.slot_mut(slot) {
+            *target = remainder;
+        }
+    }
+}
+
+/// Returns `true` if items of `item_def` may be put in `slot`.<br>
+/// Armor slots, in `ArmorSlot` order, only take armor worn there
+fn accepts(slot: InventorySlot, item_def: &ItemDefinition) -> bool {
+    match slot {
+        InventorySlot::Armor(index) => item_def.get_trait::<Armor>().is_some_and(|armor| armor.slot as usize == index),
+        _ => true
+    }
+}
+
+/// Whether the full inventory is open, and the slot picked up to be swapped
+#[derive(Default)]
+pub struct InventoryScreen {
+    pub open: bool,
+    pub held: Option<InventorySlot>,
+}
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InventoryScreen>()
+           .add_system_set(
+              ConditionSet::new()
+                .run_in_state(AppState::Finished)
+                .label("inventory")
+                .with_system(select_hotbar_slot)
+                .with_system(toggle_inventory)
+                .with_system(draw_hotbar)
+                .with_system(draw_inventory)
+                .into()
+        );
+    }
+}
+
+/// Selects a hotbar slot with the number keys, or scrolls through them with the mouse wheel
+pub fn select_hotbar_slot(
+    kb: Res<Input<KeyCode>>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    screen: Res<InventoryScreen>,
+    mut inventories: Query<&mut Inventory>,
+) {
+    let scroll = mouse_wheel.iter().map(|event| event.y).sum::<f32>();
+
+    if screen.open {
+        return;
+    }
+
+    let pressed = HOTBAR_KEYS.iter().position(|key| kb.just_pressed(*key));
+
+    for mut inventory in &mut inventories {
+        if let Some(index) = pressed {
+            if index < inventory.hotbar.len() {
+                inventory.selected = index;
+            }
+        }
+
+        // scrolling up moves the selection left
+        if scroll > 0. {
+            inventory.scroll_selection(-1);
+        } else if scroll < 0. {
+            inventory.scroll_selection(1);
+        }
+    }
+}
+
+/// Opens and closes the full inventory with <kbd>E</kbd>.<br>
+/// The camera stops looking around while it's open
+pub fn toggle_inventory(
+    kb: Res<Input<KeyCode>>,
+    mut screen: ResMut<InventoryScreen>,
+    mut cameras: Query<&mut PlayerCamera>,
+) {
+    if !kb.just_pressed(KeyCode::E) {
+        return;
+    }
+
+    screen.open = !screen.open;
+    screen.held = None;
+
+    for mut camera in &mut cameras {
+        camera.enabled = !screen.open;
+    }
+}
+
+/// What's needed to draw item icons from the item atlas
+struct ItemIcons<'a> {
+    texture_id: egui::TextureId,
+    tex_coords: &'a ItemTextureCoords,
+    item_registry: &'a ItemRegistry,
+}
+
+impl ItemIcons<'_> {
+    fn get_uvs(&self, stack: &ItemStack) -> egui::Rect {
+        let uvs = match self.item_registry.get(stack.item.get()) {
+            Some(item_def) => self.tex_coords.get_icon(item_def),
+            None => self.tex_coords.missing(),
+        };
+
+        to_egui_rect(uvs)
+    }
+
+    /// Draws a slot holding `stack`, outlined if `highlighted`
+    fn slot(&self, ui: &mut egui::Ui, stack: Option<&ItemStack>, highlighted: bool) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(SLOT_SIZE, SLOT_SIZE), egui::Sense::click());
+
+        let stroke = if highlighted {
+            egui::Stroke::new(2., egui::Color32::WHITE)
+        } else {
+            egui::Stroke::new(1., egui::Color32::DARK_GRAY)
+        };
+
+        ui.painter().rect_filled(rect, 2., egui::Color32::from_black_alpha(160));
+        ui.painter().rect_stroke(rect, 2., stroke);
+
+        if let Some(stack) = stack {
+            egui::Image::new(self.texture_id, rect.size())
+                .uv(self.get_uvs(stack))
+                .paint_at(ui, rect.shrink(4.));
+
+            if stack.count > 1 {
+                ui.painter().text(
+                    rect.right_bottom() - egui::vec2(3., 2.),
+                    egui::Align2::RIGHT_BOTTOM,
+                    stack.count,
+                    egui::FontId::proportional(14.),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+
+        response.on_hover_text(stack.map_or(String::new(), |stack| stack.item.to_string()))
+    }
+}
+
+fn to_egui_rect(rect: Rect) -> egui::Rect {
+    egui::Rect::from_min_max(
+        egui::pos2(rect.min.x, rect.min.y),
+        egui::pos2(rect.max.x, rect.max.y),
+    )
+}
+
+/// Returns the egui texture of the item atlas, once it's been built
+fn item_atlas_texture(
+    egui_context: &mut EguiContext,
+    our_atlases: &TextureAtlasHandles,
+    texture_atlases: &Assets<TextureAtlas>,
+) -> Option<egui::TextureId> {
+    let atlas = texture_atlases.get(our_atlases.item_atlas.as_ref()?)?;
+
+    Some(egui_context.add_image(atlas.texture.clone_weak()))
+}
+
+/// Draws the hotbar of the player along the bottom of the screen
+pub fn draw_hotbar(
+    mut egui_context: ResMut<EguiContext>,
+    our_atlases: Res<TextureAtlasHandles>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    tex_coords: Res<ItemTextureCoords>,
+    item_registry: Res<ItemRegistry>,
+    inventories: Query<&Inventory>,
+) {
+    let inventory = match inventories.get_single() {
+        Ok(inventory) => inventory,
+        Err(_) => return
+    };
+
+    let texture_id = match item_atlas_texture(&mut egui_context, &our_atlases, &texture_atlases) {
+        Some(texture_id) => texture_id,
+        None => return
+    };
+
+    let icons = ItemIcons { texture_id, tex_coords: &tex_coords, item_registry: &item_registry };
+
+    egui::Area::new("hotbar")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0., -10.])
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for (index, stack) in inventory.hotbar.iter().enumerate() {
+                    icons.slot(ui, stack.as_ref(), index == inventory.selected);
+                }
+            });
+        });
+}
+
+/// Draws every slot of the player's inventory while it's open.<br>
+/// Clicking two slots swaps them, shift-clicking a slot quick moves it
+#[allow(clippy::too_many_arguments)]
+pub fn draw_inventory(
+    mut egui_context: ResMut<EguiContext>,
+    mut screen: ResMut<InventoryScreen>,
+    our_atlases: Res<TextureAtlasHandles>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    tex_coords: Res<ItemTextureCoords>,
+    item_registry: Res<ItemRegistry>,
+    mut inventories: Query<&mut Inventory>,
+) {
+    if !screen.open {
+        return;
+    }
+
+    let mut inventory = match inventories.get_single_mut() {
+        Ok(inventory) => inventory,
+        Err(_) => return
+    };
+
+    let texture_id = match item_atlas_texture(&mut egui_context, &our_atlases, &texture_atlases) {
+        Some(texture_id) => texture_id,
+        None => return
+    };
+
+    let icons = ItemIcons { texture_id, tex_coords: &tex_coords, item_registry: &item_registry };
+
+    let mut clicked = None;
+
+    egui::Window::new("Inventory")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .show(egui_context.ctx_mut(), |ui| {
+            let mut row = |ui: &mut egui::Ui, slots: &mut dyn Iterator<Item = InventorySlot>| {
+                ui.horizontal(|ui| {
+                    for slot in slots {
+                        if icons.slot(ui, inventory.get(slot), screen.held == Some(slot)).clicked() {
+                            clicked = Some(slot);
+                        }
+                    }
+                });
+            };
+
+            row(ui, &mut (0..ARMOR_SLOTS).map(InventorySlot::Armor).chain([InventorySlot::Offhand]));
+
+            ui.separator();
+
+            for start in (0..MAIN_SLOTS).step_by(HOTBAR_SLOTS) {
+                row(ui, &mut (start..(start + HOTBAR_SLOTS).min(MAIN_SLOTS)).map(InventorySlot::Main));
+            }
+
+            ui.separator();
+
+            row(ui, &mut (0..HOTBAR_SLOTS).map(InventorySlot::Hotbar));
+        });
+
+    let clicked = match clicked {
+        Some(clicked) => clicked,
+        None => return
+    };
+
+    if egui_context.ctx_mut().input().modifiers.shift {
+        inventory.quick_move(clicked, &item_registry);
+        screen.held = None;
+
+        return;
+    }
+
+    match screen.held.take() {
+        Some(held) => inventory.swap(held, clicked, &item_registry),
+        None if inventory.get(clicked).is_some() => screen.held = Some(clicked),
+        None => ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        identifier::{Identifier, InternedId},
+        item_trait::{ArmorSlot, ItemTrait},
+    };
+
+    fn definition(name: &str, stack_size: i32, item_traits: Vec<Box<dyn ItemTrait>>) -> ItemDefinition {
+        ItemDefinition {
+            id: format!("blocky:{name}"),
+            stack_size,
+            item_traits,
+            texture: String::new(),
+        }
+    }
+
+    fn item_registry() -> ItemRegistry {
+        let mut item_registry = ItemRegistry::default();
+
+        item_registry.register(&Identifier::new("blocky", "dirt"), definition("dirt", 64, Vec::new())).unwrap();
+        item_registry.register(&Identifier::new("blocky", "helmet"), definition("helmet", 1, vec![
+            Box::new(Armor { slot: ArmorSlot::Head, defense: 2. }),
+        ])).unwrap();
+
+        item_registry
+    }
+
+    fn stack(item_registry: &ItemRegistry, name: &str, count: u32) -> ItemStack {
+        let id = Identifier::new("blocky", name);
+
+        ItemStack::new(item_registry.get(&id).unwrap(), InternedId::new(&id), count)
+    }
+
+    fn count(inventory: &Inventory, slot: InventorySlot) -> u32 {
+        inventory.get(slot).map_or(0, |stack| stack.count)
+    }
+
+    #[test]
+    fn insert_tops_up_partial_stacks_first() {
+        let item_registry = item_registry();
+        let mut inventory = Inventory::default();
+        inventory.main[3] = Some(stack(&item_registry, "dirt", 60));
+
+        assert_eq!(inventory.insert(stack(&item_registry, "dirt", 70), &item_registry), None);
+
+        assert_eq!(count(&inventory, InventorySlot::Main(3)), 64);
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(0)), 64);
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(1)), 2);
+    }
+
+    #[test]
+    fn insert_returns_what_doesnt_fit() {
+        let item_registry = item_registry();
+        let mut inventory = Inventory::default();
+
+        for slot in inventory.hotbar.iter_mut().chain(inventory.main.iter_mut()) {
+            *slot = Some(stack(&item_registry, "dirt", 64));
+        }
+
+        inventory.main[0] = Some(stack(&item_registry, "dirt", 60));
+
+        let leftover = inventory.insert(stack(&item_registry, "dirt", 10), &item_registry);
+
+        assert_eq!(leftover.map(|stack| stack.count), Some(6));
+        assert_eq!(count(&inventory, InventorySlot::Main(0)), 64);
+    }
+
+    #[test]
+    fn removing_more_than_a_stack_takes_all_of_it() {
+        let item_registry = item_registry();
+        let mut inventory = Inventory::default();
+        inventory.hotbar[0] = Some(stack(&item_registry, "dirt", 5));
+
+        let taken = inventory.remove(InventorySlot::Hotbar(0), 10);
+
+        assert_eq!(taken.map(|stack| stack.count), Some(5));
+        assert!(inventory.get(InventorySlot::Hotbar(0)).is_none());
+        assert!(inventory.remove(InventorySlot::Hotbar(0), 1).is_none());
+    }
+
+    #[test]
+    fn quick_move_goes_between_hotbar_and_main() {
+        let item_registry = item_registry();
+        let mut inventory = Inventory::default();
+        inventory.hotbar[4] = Some(stack(&item_registry, "dirt", 10));
+        inventory.main[2] = Some(stack(&item_registry, "dirt", 60));
+
+        inventory.quick_move(InventorySlot::Hotbar(4), &item_registry);
+
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(4)), 0);
+        assert_eq!(count(&inventory, InventorySlot::Main(2)), 64);
+        assert_eq!(count(&inventory, InventorySlot::Main(0)), 6);
+
+        inventory.quick_move(InventorySlot::Main(2), &item_registry);
+
+        assert_eq!(count(&inventory, InventorySlot::Main(2)), 0);
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(0)), 64);
+    }
+
+    #[test]
+    fn armor_slots_only_take_matching_armor() {
+        let item_registry = item_registry();
+        let mut inventory = Inventory::default();
+        inventory.hotbar[0] = Some(stack(&item_registry, "dirt", 1));
+        inventory.hotbar[1] = Some(stack(&item_registry, "helmet", 1));
+
+        inventory.swap(InventorySlot::Hotbar(0), InventorySlot::Armor(0), &item_registry);
+        inventory.swap(InventorySlot::Hotbar(1), InventorySlot::Armor(1), &item_registry);
+
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(0)), 1);
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(1)), 1);
+        assert!(inventory.armor.iter().all(Option::is_none));
+
+        inventory.quick_move(InventorySlot::Hotbar(1), &item_registry);
+        inventory.quick_move(InventorySlot::Hotbar(0), &item_registry);
+
+        assert_eq!(count(&inventory, InventorySlot::Armor(0)), 1);
+        assert_eq!(count(&inventory, InventorySlot::Main(0)), 1);
+        assert!(inventory.hotbar.iter().all(Option::is_none));
+
+        // armor can't be swapped for something that isn't armor
+        inventory.hotbar[0] = Some(stack(&item_registry, "dirt", 1));
+        inventory.swap(InventorySlot::Armor(0), InventorySlot::Hotbar(0), &item_registry);
+
+        assert_eq!(inventory.get(InventorySlot::Armor(0)).map(|stack| stack.item.get().get_name()), Some(String::from("helmet")));
+
+        inventory.hotbar[0] = None;
+        inventory.swap(InventorySlot::Armor(0), InventorySlot::Hotbar(0), &item_registry);
+
+        assert_eq!(count(&inventory, InventorySlot::Hotbar(0)), 1);
+        assert!(inventory.armor.iter().all(Option::is_none));
+    }
+}
@@ -1,34 +1,464 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Deserialize, Serialize)]
-pub enum ToolType {
-    Sword,
-    Axe,
-    Pickaxe,
-    Hoe,
-    Shovel,
-    Bow
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-pub enum ItemTrait {
-    Durability {
-        max: f32,
-        break_on_empty: bool
-    },
-    Consumable {
-        restoration: f32,
-        consumption_time: f32,
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use bevy::reflect::TypeUuid;
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    identifier::{Identifier, InternedId},
+    item_trait::{ItemTrait, ItemTraitTypes, ItemTraitsSeed, Durability, Placeable},
+};
+
+/// Stack size of the items registered for blocks
+pub const BLOCK_ITEM_STACK_SIZE: i32 = 64;
+
+/// Most items a single stack of any item can hold
+pub const MAX_STACK_SIZE: i32 = 64;
+
+/// Returned when an item definition has values that can't be used
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ItemValidationError {
+    #[error("stack size is {0}, but has to be between 1 and {MAX_STACK_SIZE}")]
+    StackSizeOutOfRange(i32),
+
+    #[error("stack size is {0}, but items with `Durability` can't stack")]
+    StackableWithDurability(i32),
+
+    #[error("has the `{0}` trait more than once")]
+    DuplicateTrait(String),
+
+    #[error("has both the `{first}` and `{second}` traits, which can't be combined")]
+    ExclusiveTraits {
+        first: String,
+        second: String,
     },
-    Tool {
-        base_damage: f32,
-        tool_type: ToolType,
+
+    #[error("`{item_trait}` has `{field}` set to {value}, but it can't be negative")]
+    NegativeValue {
+        item_trait: String,
+        field: &'static str,
+        value: f32,
     },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// An item, loaded from `assets/data/<namespace>/items/<name>.ron`.<br>
+/// Read with `ItemDefinitionSeed`, since its traits depend on the registered `ItemTraitTypes`
+#[derive(Debug, Clone, Serialize, TypeUuid)]
+#[uuid = "89857523-b4c1-450a-ae32-4f06ce62cdab"]
 pub struct ItemDefinition {
     pub id: String,
     pub stack_size: i32,
-    pub item_traits: Vec<ItemTrait>,
-}
\ No newline at end of file
+
+    /// What the item does, read as the types registered in `ItemTraitTypes`
+    pub item_traits: Vec<Box<dyn ItemTrait>>,
+
+    /// Icon texture in `textures/item/`, the item's name if empty
+    pub texture: String,
+}
+
+/// Reads an `ItemDefinition`, with its traits read as the types in `ItemTraitTypes`.<br>
+/// `texture` can be left out, and unknown fields are ignored
+pub struct ItemDefinitionSeed<'a>(pub &'a ItemTraitTypes);
+
+impl<'de> DeserializeSeed<'de> for ItemDefinitionSeed<'_> {
+    type Value = ItemDefinition;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("ItemDefinition", &["id", "stack_size", "item_traits", "texture"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for ItemDefinitionSeed<'_> {
+    type Value = ItemDefinition;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an item definition")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut id = None;
+        let mut stack_size = None;
+        let mut item_traits = None;
+        let mut texture = None;
+
+        #[derive(Deserialize)]
+        #[serde(field_identifier, rename_all = "snake_case")]
+        enum Field {
+            Id,
+            StackSize,
+            ItemTraits,
+            Texture,
+            #[serde(other)]
+            Unknown,
+        }
+
+        while let Some(field) = map.next_key::<Field>()? {
+            match field {
+                Field::Id => id = Some(map.next_value()?),
+                Field::StackSize => stack_size = Some(map.next_value()?),
+                Field::ItemTraits => item_traits = Some(map.next_value_seed(ItemTraitsSeed(self.0))?),
+                Field::Texture => texture = Some(map.next_value()?),
+                Field::Unknown => { map.next_value::<IgnoredAny>()?; }
+            }
+        }
+
+        Ok(ItemDefinition {
+            id: id.ok_or_else(|| de::Error::missing_field("id"))?,
+            stack_size: stack_size.ok_or_else(|| de::Error::missing_field("stack_size"))?,
+            item_traits: item_traits.ok_or_else(|| de::Error::missing_field("item_traits"))?,
+            texture: texture.unwrap_or_default(),
+        })
+    }
+}
+
+impl ItemDefinition {
+    /// The item that places the block `block`, registered along with it
+    pub fn block_item(block: &Identifier) -> Self {
+        Self {
+            id: block.to_string(),
+            stack_size: BLOCK_ITEM_STACK_SIZE,
+            item_traits: vec![Box::new(Placeable { block: block.clone() })],
+            texture: String::new(),
+        }
+    }
+
+    /// Returns the first trait of type `T` this item has
+    pub fn get_trait<T: ItemTrait>(&self) -> Option<&T> {
+        self.item_traits.iter().find_map(|item_trait| item_trait.downcast_ref::<T>())
+    }
+
+    pub fn has_trait<T: ItemTrait>(&self) -> bool {
+        self.item_traits.iter().any(|item_trait| item_trait.is::<T>())
+    }
+
+    /// The block this item places, if it places one
+    pub fn get_placed_block(&self) -> Option<Identifier> {
+        self.get_trait::<Placeable>().map(|placeable| placeable.block.clone())
+    }
+
+    /// Returns every problem with this definition's stack size and traits
+    pub fn validate(&self) -> Vec<ItemValidationError> {
+        let mut errors = Vec::new();
+
+        if !(1..=MAX_STACK_SIZE).contains(&self.stack_size) {
+            errors.push(ItemValidationError::StackSizeOutOfRange(self.stack_size));
+        } else if self.stack_size > 1 && self.has_trait::<Durability>() {
+            errors.push(ItemValidationError::StackableWithDurability(self.stack_size));
+        }
+
+        let get_name = |item_trait: &dyn ItemTrait| String::from(item_trait.get_name());
+
+        for (index, item_trait) in self.item_traits.iter().enumerate() {
+            let item_trait = item_trait.as_ref();
+            let earlier_traits = &self.item_traits[..index];
+
+            let copies = earlier_traits.iter().filter(|earlier| earlier.is_same_kind(item_trait)).count();
+
+            // later copies of a trait are only reported once, and aren't checked again
+            if copies > 0 {
+                if copies == 1 {
+                    errors.push(ItemValidationError::DuplicateTrait(get_name(item_trait)));
+                }
+
+                continue;
+            }
+
+            for earlier in earlier_traits.iter().filter(|earlier| earlier.excludes(item_trait)) {
+                errors.push(ItemValidationError::ExclusiveTraits {
+                    first: get_name(earlier.as_ref()),
+                    second: get_name(item_trait),
+                });
+            }
+
+            for (field, value) in item_trait.get_numeric_fields() {
+                if value < 0. {
+                    errors.push(ItemValidationError::NegativeValue {
+                        item_trait: get_name(item_trait),
+                        field,
+                        value,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Most items a single stack of this item can hold
+    pub fn get_stack_size(&self) -> u32 {
+        self.stack_size.max(1) as u32
+    }
+
+    /// Path of the item's icon texture
+    pub fn get_texture_path(&self) -> Option<String> {
+        match Identifier::from_str(&self.id) {
+            Ok(id) => {
+                let texture = if self.texture.is_empty() {
+                    id.get_name()
+                } else {
+                    self.texture.clone()
+                };
+
+                Some(format!("textures/item/{}.png", texture))
+            },
+            Err(err) => {
+                println!("[Error] {err}");
+                None
+            }
+        }
+    }
+
+    /// Durability of a brand new item, or `None` if it doesn't wear out
+    pub fn get_max_durability(&self) -> Option<f32> {
+        self.get_trait::<Durability>().map(|durability| durability.max)
+    }
+}
+
+/// A number of the same item, like the contents of an inventory slot
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ItemStack {
+    pub item: InternedId,
+    pub count: u32,
+
+    /// Durability left, for items with the `Durability` trait
+    #[serde(default)]
+    pub durability: Option<f32>,
+
+    /// Anything else about this stack, like a custom name.<br>
+    /// Only stacks with the same data stack together
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+}
+
+impl ItemStack {
+    /// A stack of brand new items
+    pub fn new(item_def: &ItemDefinition, item: InternedId, count: u32) -> Self {
+        Self {
+            item,
+            count,
+            durability: item_def.get_max_durability(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.count == 0 }
+
+    /// Returns `true` if `other` can be added to this stack.<br>
+    /// Items that wear out never stack, just like `ItemDefinition::validate` requires,
+    /// so no durability is lost or gained by merging
+    pub fn can_stack_with(&self, other: &ItemStack, item_def: &ItemDefinition) -> bool {
+        self.item == other.item &&
+        self.data == other.data &&
+        item_def.get_max_durability().is_none()
+    }
+
+    /// Moves as many items from `other` into this stack as `stack_size` allows.<br>
+    /// Returns what's left of `other`, or `None` if all of it fit
+    pub fn merge(&mut self, mut other: ItemStack, item_def: &ItemDefinition) -> Option<ItemStack> {
+        if other.is_empty() {
+            return None;
+        }
+
+        if !self.can_stack_with(&other, item_def) {
+            return Some(other);
+        }
+
+        let moved = other.count.min(item_def.get_stack_size().saturating_sub(self.count));
+
+        self.count += moved;
+        other.count -= moved;
+
+        if other.is_empty() {
+            None
+        } else {
+            Some(other)
+        }
+    }
+
+    /// Takes up to `count` items off this stack into a new one.<br>
+    /// Returns `None` if nothing was taken
+    pub fn split(&mut self, count: u32) -> Option<ItemStack> {
+        let taken = count.min(self.count);
+
+        if taken == 0 {
+            return None;
+        }
+
+        self.count -= taken;
+
+        Some(ItemStack {
+            count: taken,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::item_trait::{Armor, ArmorSlot, Consumable, Fuel};
+
+    fn definition(stack_size: i32, item_traits: Vec<Box<dyn ItemTrait>>) -> ItemDefinition {
+        ItemDefinition {
+            id: String::from("blocky:test"),
+            stack_size,
+            item_traits,
+            texture: String::new(),
+        }
+    }
+
+    fn pickaxe() -> ItemDefinition {
+        definition(1, vec![Box::new(Durability { max: 100., break_on_empty: true })])
+    }
+
+    fn stack(item_def: &ItemDefinition, name: &str, count: u32) -> ItemStack {
+        ItemStack::new(item_def, InternedId::new(&Identifier::new("blocky", name)), count)
+    }
+
+    #[test]
+    fn stack_size_must_be_in_range() {
+        assert_eq!(definition(0, Vec::new()).validate(), vec![ItemValidationError::StackSizeOutOfRange(0)]);
+        assert_eq!(definition(-5, Vec::new()).validate(), vec![ItemValidationError::StackSizeOutOfRange(-5)]);
+        assert_eq!(definition(MAX_STACK_SIZE + 1, Vec::new()).validate(), vec![ItemValidationError::StackSizeOutOfRange(MAX_STACK_SIZE + 1)]);
+        assert!(definition(MAX_STACK_SIZE, Vec::new()).validate().is_empty());
+    }
+
+    #[test]
+    fn items_with_durability_dont_stack() {
+        let mut stackable = pickaxe();
+        stackable.stack_size = 4;
+
+        assert_eq!(stackable.validate(), vec![ItemValidationError::StackableWithDurability(4)]);
+        assert!(pickaxe().validate().is_empty());
+    }
+
+    #[test]
+    fn duplicate_traits_are_reported_once() {
+        let item_def = definition(1, vec![
+            Box::new(Fuel { burn_time: 1. }),
+            Box::new(Fuel { burn_time: 2. }),
+            Box::new(Fuel { burn_time: -3. }),
+        ]);
+
+        assert_eq!(item_def.validate(), vec![ItemValidationError::DuplicateTrait(String::from("Fuel"))]);
+    }
+
+    #[test]
+    fn exclusive_traits_are_rejected() {
+        let item_def = definition(1, vec![
+            Box::new(Placeable { block: Identifier::new("blocky", "stone") }),
+            Box::new(Armor { slot: ArmorSlot::Head, defense: 2. }),
+        ]);
+
+        assert_eq!(item_def.validate(), vec![ItemValidationError::ExclusiveTraits {
+            first: String::from("Placeable"),
+            second: String::from("Armor"),
+        }]);
+    }
+
+    #[test]
+    fn numeric_fields_cant_be_negative() {
+        let item_def = definition(1, vec![
+            Box::new(Consumable { restoration: -1., consumption_time: 2. }),
+        ]);
+
+        assert_eq!(item_def.validate(), vec![ItemValidationError::NegativeValue {
+            item_trait: String::from("Consumable"),
+            field: "restoration",
+            value: -1.,
+        }]);
+    }
+
+    #[test]
+    fn merge_fits_whole_stack() {
+        let item_def = definition(64, Vec::new());
+        let mut target = stack(&item_def, "dirt", 10);
+
+        assert_eq!(target.merge(stack(&item_def, "dirt", 20), &item_def), None);
+        assert_eq!(target.count, 30);
+    }
+
+    #[test]
+    fn merge_overflow_returns_remainder() {
+        let item_def = definition(64, Vec::new());
+        let mut target = stack(&item_def, "dirt", 60);
+
+        let remainder = target.merge(stack(&item_def, "dirt", 10), &item_def).unwrap();
+
+        assert_eq!(target.count, 64);
+        assert_eq!(remainder.count, 6);
+    }
+
+    #[test]
+    fn merge_into_full_stack_returns_everything() {
+        let item_def = definition(16, Vec::new());
+        let mut target = stack(&item_def, "dirt", 16);
+
+        let remainder = target.merge(stack(&item_def, "dirt", 5), &item_def).unwrap();
+
+        assert_eq!(target.count, 16);
+        assert_eq!(remainder.count, 5);
+    }
+
+    #[test]
+    fn different_items_dont_stack() {
+        let item_def = definition(64, Vec::new());
+        let mut target = stack(&item_def, "dirt", 1);
+        let other = stack(&item_def, "stone", 1);
+
+        assert!(!target.can_stack_with(&other, &item_def));
+        assert_eq!(target.merge(other.clone(), &item_def), Some(other));
+        assert_eq!(target.count, 1);
+    }
+
+    #[test]
+    fn different_data_doesnt_stack() {
+        let item_def = definition(64, Vec::new());
+        let target = stack(&item_def, "dirt", 1);
+        let mut other = stack(&item_def, "dirt", 1);
+
+        other.data.insert(String::from("name"), String::from("Special dirt"));
+
+        assert!(!target.can_stack_with(&other, &item_def));
+    }
+
+    #[test]
+    fn damaged_tools_dont_stack() {
+        let item_def = pickaxe();
+        let mut target = stack(&item_def, "pickaxe", 1);
+        let mut damaged = stack(&item_def, "pickaxe", 1);
+
+        damaged.durability = Some(40.);
+
+        assert!(!target.can_stack_with(&damaged, &item_def));
+        assert_eq!(target.merge(damaged.clone(), &item_def), Some(damaged));
+    }
+
+    #[test]
+    fn undamaged_tools_dont_stack_either() {
+        let item_def = pickaxe();
+        let mut target = stack(&item_def, "pickaxe", 1);
+        let other = stack(&item_def, "pickaxe", 1);
+
+        assert!(!target.can_stack_with(&other, &item_def));
+        assert_eq!(target.merge(other.clone(), &item_def), Some(other));
+        assert_eq!(target.count, 1);
+    }
+
+    #[test]
+    fn split_takes_at_most_whole_stack() {
+        let item_def = definition(64, Vec::new());
+        let mut source = stack(&item_def, "dirt", 10);
+
+        assert_eq!(source.split(4).unwrap().count, 4);
+        assert_eq!(source.count, 6);
+
+        assert_eq!(source.split(100).unwrap().count, 6);
+        assert!(source.is_empty());
+
+        assert_eq!(source.split(1), None);
+    }
+}
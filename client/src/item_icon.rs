@@ -0,0 +1,290 @@
+use bevy::{
+    prelude::*,
+    sprite::Rect,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    block::{BlockFace, BlockState},
+    procedural::Climate,
+    texture_atlas::get_atlas_pixel_rect,
+    tint::Colormaps,
+};
+
+/// Width and height in pixels of generated block icons
+pub const ICON_SIZE: u32 = 32;
+
+// brightness of each visible side, so the cube looks lit from above
+const TOP_SHADE: f32 = 1.;
+const LEFT_SHADE: f32 = 0.8;
+const FRONT_SHADE: f32 = 0.6;
+
+/// A texture drawn on one side of a block icon
+#[derive(Debug, Clone, Copy)]
+pub struct IconLayer {
+    /// Atlas coordinates of the texture
+    pub tex_coords: Rect,
+    /// Linear RGBA color the texture is multiplied by, like a vertex color
+    pub color: [f32; 4],
+}
+
+/// Textures on the three visible sides of a block icon, bottom layer first
+#[derive(Debug, Clone, Default)]
+pub struct IconFaces {
+    pub top: Vec<IconLayer>,
+    pub left: Vec<IconLayer>,
+    pub front: Vec<IconLayer>,
+}
+
+impl IconFaces {
+    /// The faces of `state` with their layers, tinted as they are in the default climate.<br>
+    /// Models are drawn as full cubes
+    pub fn from_state(state: &BlockState, colormaps: &Colormaps) -> Self {
+        let climate = Climate::default();
+
+        let get_layers = |block_face: BlockFace| {
+            let base = IconLayer {
+                tex_coords: state.get_face_texture(block_face),
+                color: state.get_tint_color(block_face, colormaps, climate),
+            };
+
+            let layers = state.get_layers(block_face).iter().map(|layer| IconLayer {
+                tex_coords: layer.tex_coords,
+                color: state.get_layer_color(layer, colormaps, climate),
+            });
+
+            std::iter::once(base).chain(layers).collect()
+        };
+
+        Self {
+            top: get_layers(BlockFace::Top),
+            left: get_layers(BlockFace::Left),
+            front: get_layers(BlockFace::Front),
+        }
+    }
+}
+
+/// A face texture read straight out of the atlas, tinted and ready to be sampled
+struct IconTexture<'a> {
+    atlas_data: &'a [u8],
+    atlas_width: u32,
+    /// Pixel position and size of the texture in the atlas
+    min: UVec2,
+    size: UVec2,
+    color: [f32; 4],
+}
+
+impl<'a> IconTexture<'a> {
+    fn new(atlas_image: &'a Image, layer: &IconLayer) -> Option<Self> {
+        let (min, size) = get_atlas_pixel_rect(atlas_image, layer.tex_coords)?;
+
+        // vertex colors are linear, but the pixels are sRGB
+        let color = Color::rgba_linear(layer.color[0], layer.color[1], layer.color[2], layer.color[3]).as_rgba_f32();
+
+        Some(Self {
+            atlas_data: &atlas_image.data,
+            atlas_width: atlas_image.texture_descriptor.size.width,
+            min,
+            size,
+            color,
+        })
+    }
+
+    /// Tinted color of the pixel at `u`, `v`, both from 0 to 1
+    fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = self.min.x + ((u * self.size.x as f32) as u32).min(self.size.x - 1);
+        let y = self.min.y + ((v * self.size.y as f32) as u32).min(self.size.y - 1);
+
+        let start = ((y * self.atlas_width + x) * 4) as usize;
+        let pixel = &self.atlas_data[start..start + 4];
+
+        [0, 1, 2, 3].map(|channel| pixel[channel] as f32 / 255. * self.color[channel])
+    }
+}
+
+/// One side of the cube on the icon: a parallelogram starting at `origin`,
+/// with `u_axis` and `v_axis` following the texture's width and height
+struct IconSide {
+    origin: Vec2,
+    u_axis: Vec2,
+    v_axis: Vec2,
+    shade: f32,
+}
+
+impl IconSide {
+    /// Texture coordinates of `point` on this side, if it's inside it
+    fn get_uv(&self, point: Vec2) -> Option<Vec2> {
+        let offset = point - self.origin;
+        let determinant = self.u_axis.perp_dot(self.v_axis);
+
+        let uv = Vec2::new(
+            offset.perp_dot(self.v_axis) / determinant,
+            self.u_axis.perp_dot(offset) / determinant,
+        );
+
+        (uv.cmpge(Vec2::ZERO).all() && uv.cmplt(Vec2::ONE).all()).then_some(uv)
+    }
+}
+
+/// Draws a shaded isometric cube out of the textures in `faces`, on the CPU.<br>
+/// The top is drawn as seen from above, the left face on the left
+/// and the front face on the right.<br>
+/// `atlas_image` has to be `Rgba8UnormSrgb`; its pixels are read in place.<br>
+/// Returns `None` if a texture can't be read out of `atlas_image`
+pub fn build_block_icon(atlas_image: &Image, faces: &IconFaces) -> Option<Image> {
+    let size = ICON_SIZE as f32;
+
+    let textures = |layers: &[IconLayer]| layers.iter()
+        .map(|layer| IconTexture::new(atlas_image, layer))
+        .collect::<Option<Vec<_>>>();
+
+    let sides = [
+        (IconSide {
+            origin: Vec2::new(0., size / 4.),
+            u_axis: Vec2::new(size / 2., -size / 4.),
+            v_axis: Vec2::new(size / 2., size / 4.),
+            shade: TOP_SHADE,
+        }, textures(&faces.top)?),
+        (IconSide {
+            origin: Vec2::new(0., size / 4.),
+            u_axis: Vec2::new(size / 2., size / 4.),
+            v_axis: Vec2::new(0., size / 2.),
+            shade: LEFT_SHADE,
+        }, textures(&faces.left)?),
+        (IconSide {
+            origin: Vec2::new(size / 2., size / 2.),
+            u_axis: Vec2::new(size / 2., -size / 4.),
+            v_axis: Vec2::new(0., size / 2.),
+            shade: FRONT_SHADE,
+        }, textures(&faces.front)?),
+    ];
+
+    let mut data = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let point = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+            let pixel = sides.iter()
+                .find_map(|(side, layers)| side.get_uv(point).map(|uv| (side.shade, layers, uv)))
+                .map_or([0.; 4], |(shade, layers, uv)| {
+                    let color = layers.iter().fold([0.; 4], |below, layer| blend(below, layer.sample(uv.x, uv.y)));
+
+                    [color[0] * shade, color[1] * shade, color[2] * shade, color[3]]
+                });
+
+            data.extend(pixel.map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8));
+        }
+    }
+
+    Some(Image::new(
+        Extent3d {
+            width: ICON_SIZE,
+            height: ICON_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+}
+
+/// Draws `above` over `below`, blending by alpha
+fn blend(below: [f32; 4], above: [f32; 4]) -> [f32; 4] {
+    let alpha = above[3] + below[3] * (1. - above[3]);
+
+    if alpha <= 0. {
+        return [0.; 4];
+    }
+
+    let channel = |index: usize| (above[index] * above[3] + below[index] * below[3] * (1. - above[3])) / alpha;
+
+    [channel(0), channel(1), channel(2), alpha]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tint::NO_TINT;
+
+    /// A 2x1 atlas of two flat colored tiles, 4 pixels each
+    fn atlas(left: [u8; 4], right: [u8; 4]) -> Image {
+        let mut data = Vec::new();
+
+        for _ in 0..4 {
+            for _ in 0..4 { data.extend(left); }
+            for _ in 0..4 { data.extend(right); }
+        }
+
+        Image::new(
+            Extent3d { width: 8, height: 4, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    fn layer(tile: u32, color: [f32; 4]) -> IconLayer {
+        IconLayer {
+            tex_coords: Rect {
+                min: Vec2::new(tile as f32 * 0.5, 0.),
+                max: Vec2::new(tile as f32 * 0.5 + 0.5, 1.),
+            },
+            color,
+        }
+    }
+
+    fn faces(layers: Vec<IconLayer>) -> IconFaces {
+        IconFaces {
+            top: layers.clone(),
+            left: layers.clone(),
+            front: layers,
+        }
+    }
+
+    fn pixel(icon: &Image, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * ICON_SIZE + x) * 4) as usize;
+
+        icon.data[start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn sides_are_shaded() {
+        let atlas = atlas([200, 100, 50, 255], [0, 0, 0, 255]);
+        let icon = build_block_icon(&atlas, &faces(vec![layer(0, NO_TINT)])).unwrap();
+
+        assert_eq!(icon.texture_descriptor.size.width, ICON_SIZE);
+        assert_eq!(icon.data.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+
+        assert_eq!(pixel(&icon, ICON_SIZE / 2, ICON_SIZE / 4), [200, 100, 50, 255]);
+        assert_eq!(pixel(&icon, ICON_SIZE / 4, ICON_SIZE * 5 / 8), [160, 80, 40, 255]);
+        assert_eq!(pixel(&icon, ICON_SIZE * 3 / 4, ICON_SIZE * 5 / 8), [120, 60, 30, 255]);
+    }
+
+    #[test]
+    fn corners_are_transparent() {
+        let atlas = atlas([255; 4], [255; 4]);
+        let icon = build_block_icon(&atlas, &faces(vec![layer(0, NO_TINT)])).unwrap();
+
+        for (x, y) in [(0, 0), (ICON_SIZE - 1, 0), (0, ICON_SIZE - 1), (ICON_SIZE - 1, ICON_SIZE - 1)] {
+            assert_eq!(pixel(&icon, x, y)[3], 0);
+        }
+    }
+
+    #[test]
+    fn layers_are_drawn_over_the_base() {
+        let atlas = atlas([255, 255, 255, 255], [0, 0, 255, 255]);
+        let icon = build_block_icon(&atlas, &faces(vec![layer(0, NO_TINT), layer(1, NO_TINT)])).unwrap();
+
+        assert_eq!(pixel(&icon, ICON_SIZE / 2, ICON_SIZE / 4), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn tint_multiplies_the_texture() {
+        let atlas = atlas([255; 4], [255; 4]);
+        let icon = build_block_icon(&atlas, &faces(vec![layer(0, [0., 1., 0., 1.])])).unwrap();
+
+        assert_eq!(pixel(&icon, ICON_SIZE / 2, ICON_SIZE / 4), [0, 255, 0, 255]);
+    }
+}
@@ -0,0 +1,514 @@
+use std::{
+    any::Any,
+    fmt::{self, Debug},
+    sync::{Arc, RwLock},
+};
+
+use bevy::{prelude::*, ecs::system::EntityCommands};
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, EnumAccess, SeqAccess, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::identifier::{Identifier, InternedId};
+
+/// How much faster each tool tier mines the blocks it's made for
+pub const TOOL_SPEED_PER_TIER: f32 = 2.;
+
+/// Something an item does or has, like wearing out or burning as fuel.<br>
+/// Item definitions list them as `Name(field: value, ...)`, where `Name` is
+/// the trait's `name`, once it's been registered in `ItemTraitTypes`.<br>
+/// Every item trait is also a component, for entities that carry an item's traits
+pub trait ItemTrait: ItemTraitObject + Send + Sync + Debug + 'static {
+    /// Name the trait is written as in item definitions, like `Durability`
+    fn name() -> &'static str where Self: Sized;
+
+    /// Numeric fields of this trait by name, none of which can be negative
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> { Vec::new() }
+
+    /// Names of the traits an item with this trait can't also have
+    fn get_exclusive_traits(&self) -> &'static [&'static str] { &[] }
+}
+
+/// What's needed to use an `ItemTrait` as a trait object,
+/// implemented for every item trait
+pub trait ItemTraitObject {
+    /// The trait's `name`
+    fn get_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize;
+
+    fn clone_box(&self) -> Box<dyn ItemTrait>;
+
+    /// Adds a copy of this trait to `entity` as a component
+    fn insert(&self, entity: &mut EntityCommands);
+}
+
+impl<T: ItemTrait + Component + Clone + Serialize> ItemTraitObject for T {
+    fn get_name(&self) -> &'static str { T::name() }
+
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize { self }
+
+    fn clone_box(&self) -> Box<dyn ItemTrait> { Box::new(self.clone()) }
+
+    fn insert(&self, entity: &mut EntityCommands) {
+        entity.insert(self.clone());
+    }
+}
+
+impl dyn ItemTrait {
+    pub fn is<T: ItemTrait>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    pub fn downcast_ref<T: ItemTrait>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns `true` if `self` and `other` are the same kind of trait
+    pub fn is_same_kind(&self, other: &dyn ItemTrait) -> bool {
+        self.as_any().type_id() == other.as_any().type_id()
+    }
+
+    /// Returns `true` if an item can't have both `self` and `other`
+    pub fn excludes(&self, other: &dyn ItemTrait) -> bool {
+        let excluded_by = |item_trait: &dyn ItemTrait, other: &dyn ItemTrait|
+            item_trait.get_exclusive_traits().contains(&other.get_name());
+
+        excluded_by(self, other) || excluded_by(other, self)
+    }
+}
+
+impl Clone for Box<dyn ItemTrait> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Serialize for dyn ItemTrait {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_variant("ItemTrait", 0, self.get_name(), self.as_serialize())
+    }
+}
+
+type DeserializeFn = for<'de> fn(&mut dyn erased_serde::Deserializer<'de>) -> Result<Box<dyn ItemTrait>, erased_serde::Error>;
+
+/// A kind of item trait that item definitions can use
+#[derive(Clone, Copy)]
+struct ItemTraitType {
+    name: &'static str,
+    deserialize: DeserializeFn,
+}
+
+fn deserialize_item_trait<T: ItemTrait + Component + Clone + Serialize + DeserializeOwned>(
+    deserializer: &mut dyn erased_serde::Deserializer,
+) -> Result<Box<dyn ItemTrait>, erased_serde::Error> {
+    Ok(Box::new(erased_serde::deserialize::<T>(deserializer)?))
+}
+
+/// Every kind of item trait that item definitions can use.<br>
+/// Clones share the same types, so `DefinitionLoader` keeps a clone
+/// of the resource to read definitions off the main thread
+#[derive(Clone, Default)]
+pub struct ItemTraitTypes(Arc<RwLock<Vec<ItemTraitType>>>);
+
+impl ItemTraitTypes {
+    /// Item trait types with every built-in trait registered
+    pub fn with_builtins() -> Self {
+        let trait_types = Self::default();
+
+        trait_types.register::<Durability>();
+        trait_types.register::<Consumable>();
+        trait_types.register::<Tool>();
+        trait_types.register::<Placeable>();
+        trait_types.register::<Fuel>();
+        trait_types.register::<Armor>();
+        trait_types.register::<Throwable>();
+
+        trait_types
+    }
+
+    /// Lets item definitions use `T`, written as `T::name()`.<br>
+    /// Registering a name again replaces the type it's read as
+    pub fn register<T: ItemTrait + Component + Clone + Serialize + DeserializeOwned>(&self) {
+        // nothing can panic while holding the lock
+        let mut trait_types = self.0.write().unwrap();
+
+        trait_types.retain(|trait_type| trait_type.name != T::name());
+        trait_types.push(ItemTraitType {
+            name: T::name(),
+            deserialize: deserialize_item_trait::<T>,
+        });
+    }
+
+    /// Names of every registered kind of item trait, sorted
+    pub fn get_names(&self) -> Vec<&'static str> {
+        let mut names = self.0.read().unwrap().iter()
+            .map(|trait_type| trait_type.name)
+            .collect::<Vec<_>>();
+
+        names.sort_unstable();
+        names
+    }
+
+    fn get(&self, name: &str) -> Option<ItemTraitType> {
+        self.0.read().unwrap().iter()
+            .find(|trait_type| trait_type.name == name)
+            .copied()
+    }
+}
+
+/// Reads a list of item traits as the types registered in `ItemTraitTypes`
+pub struct ItemTraitsSeed<'a>(pub &'a ItemTraitTypes);
+
+impl<'de> DeserializeSeed<'de> for ItemTraitsSeed<'_> {
+    type Value = Vec<Box<dyn ItemTrait>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ItemTraitsSeed<'_> {
+    type Value = Vec<Box<dyn ItemTrait>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of item traits")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut item_traits = Vec::new();
+
+        while let Some(item_trait) = seq.next_element_seed(ItemTraitSeed(self.0))? {
+            item_traits.push(item_trait);
+        }
+
+        Ok(item_traits)
+    }
+}
+
+/// Reads one item trait as the type registered in `ItemTraitTypes` under its name
+pub struct ItemTraitSeed<'a>(pub &'a ItemTraitTypes);
+
+impl<'de> DeserializeSeed<'de> for ItemTraitSeed<'_> {
+    type Value = Box<dyn ItemTrait>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        // the names are checked by `visit_enum`, as they aren't known until runtime
+        deserializer.deserialize_enum("ItemTrait", &[], self)
+    }
+}
+
+impl<'de> Visitor<'de> for ItemTraitSeed<'_> {
+    type Value = Box<dyn ItemTrait>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an item trait, like `Durability(max: 100.0, break_on_empty: true)`")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (TraitName(name), variant) = data.variant()?;
+
+        match self.0.get(&name) {
+            Some(trait_type) => variant.newtype_variant_seed(TraitFieldsSeed(trait_type.deserialize)),
+            None => Err(de::Error::custom(format!(
+                "unknown item trait `{name}`, expected one of: {}",
+                self.0.get_names().join(", ")
+            )))
+        }
+    }
+}
+
+/// Name of an item trait, written like an enum variant
+struct TraitName(String);
+
+impl<'de> Deserialize<'de> for TraitName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TraitNameVisitor;
+
+        impl<'de> Visitor<'de> for TraitNameVisitor {
+            type Value = TraitName;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the name of an item trait")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(TraitName(String::from(name)))
+            }
+        }
+
+        deserializer.deserialize_identifier(TraitNameVisitor)
+    }
+}
+
+/// Reads the fields of an item trait as the type it was registered with
+struct TraitFieldsSeed(DeserializeFn);
+
+impl<'de> DeserializeSeed<'de> for TraitFieldsSeed {
+    type Value = Box<dyn ItemTrait>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut deserializer = <dyn erased_serde::Deserializer>::erase(deserializer);
+
+        (self.0)(&mut deserializer).map_err(de::Error::custom)
+    }
+}
+
+/// The item wears out as it's used
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Durability {
+    pub max: f32,
+    pub break_on_empty: bool,
+}
+
+impl ItemTrait for Durability {
+    fn name() -> &'static str { "Durability" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("max", self.max)]
+    }
+}
+
+/// The item can be eaten
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Consumable {
+    pub restoration: f32,
+    pub consumption_time: f32,
+}
+
+impl ItemTrait for Consumable {
+    fn name() -> &'static str { "Consumable" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("restoration", self.restoration), ("consumption_time", self.consumption_time)]
+    }
+
+    fn get_exclusive_traits(&self) -> &'static [&'static str] { &["Placeable", "Armor"] }
+}
+
+/// The item is a tool.<br>
+/// `tool_type` is an ID like `blocky:pickaxe`, so data packs can add their own.<br>
+/// Blocks in the tool type's `mineable_<name>` tag break faster with it
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Tool {
+    pub base_damage: f32,
+    pub tool_type: Identifier,
+    /// How good the tool's material is, starting at 0.
+    /// Higher tiers mine faster, and some blocks only drop with a high enough tier
+    #[serde(default)]
+    pub tier: u32,
+}
+
+impl Tool {
+    /// How many times faster than a hand the tool mines blocks it's made for
+    pub fn get_mining_speed(&self) -> f32 {
+        TOOL_SPEED_PER_TIER * (self.tier + 1) as f32
+    }
+
+    /// The block tag of blocks this tool is made for, like `blocky:mineable_pickaxe`.<br>
+    /// Returns `None` if no block was ever tagged with it
+    pub fn get_mineable_tag(&self) -> Option<InternedId> {
+        let tag = Identifier::new(
+            &self.tool_type.get_namespace(),
+            &format!("mineable_{}", self.tool_type.get_name())
+        );
+
+        InternedId::find(&tag)
+    }
+}
+
+impl ItemTrait for Tool {
+    fn name() -> &'static str { "Tool" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("base_damage", self.base_damage)]
+    }
+
+    fn get_exclusive_traits(&self) -> &'static [&'static str] { &["Placeable", "Armor"] }
+}
+
+/// The item places `block` when used
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Placeable {
+    pub block: Identifier,
+}
+
+impl ItemTrait for Placeable {
+    fn name() -> &'static str { "Placeable" }
+
+    fn get_exclusive_traits(&self) -> &'static [&'static str] { &["Tool", "Armor", "Consumable"] }
+}
+
+/// The item burns in a furnace for `burn_time` seconds
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Fuel {
+    pub burn_time: f32,
+}
+
+impl ItemTrait for Fuel {
+    fn name() -> &'static str { "Fuel" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("burn_time", self.burn_time)]
+    }
+}
+
+/// Where a piece of armor is worn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ArmorSlot {
+    Head,
+    Chest,
+    Legs,
+    Feet,
+}
+
+/// The item can be worn as armor
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Armor {
+    pub slot: ArmorSlot,
+    pub defense: f32,
+}
+
+impl ItemTrait for Armor {
+    fn name() -> &'static str { "Armor" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("defense", self.defense)]
+    }
+
+    fn get_exclusive_traits(&self) -> &'static [&'static str] { &["Tool", "Placeable", "Consumable"] }
+}
+
+/// The item can be thrown
+#[derive(Component, Debug, Clone, Deserialize, Serialize)]
+pub struct Throwable {
+    /// Speed the item leaves the hand at, in blocks per second
+    pub speed: f32,
+}
+
+impl ItemTrait for Throwable {
+    fn name() -> &'static str { "Throwable" }
+
+    fn get_numeric_fields(&self) -> Vec<(&'static str, f32)> {
+        vec![("speed", self.speed)]
+    }
+}
+
+/// Adds the `ItemTraitTypes` resource with every built-in trait,
+/// before any item definition is read.<br>
+/// Other plugins can register their own traits in it after this one
+pub struct ItemTraitsPlugin;
+
+impl Plugin for ItemTraitsPlugin {
+    fn build(&self, app: &mut App) {
+        app.world.get_resource_or_insert_with(ItemTraitTypes::with_builtins);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ron::extensions::Extensions;
+
+    use crate::item::{ItemDefinition, ItemDefinitionSeed};
+
+    fn parse(trait_types: &ItemTraitTypes, source: &str) -> Result<ItemDefinition, ron::Error> {
+        ron::Options::default()
+            .with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES)
+            .from_str_seed(source, ItemDefinitionSeed(trait_types))
+    }
+
+    #[test]
+    fn reads_registered_traits() {
+        let item_def = parse(&ItemTraitTypes::with_builtins(), r#"(
+            id: "blocky:pickaxe",
+            stack_size: 1,
+            item_traits: [
+                Durability(max: 100.0, break_on_empty: true),
+                Tool(base_damage: 2.0, tool_type: "pickaxe"),
+                Armor(slot: Head, defense: 1.0),
+            ],
+        )"#).unwrap();
+
+        assert_eq!(item_def.get_trait::<Durability>().unwrap().max, 100.);
+        assert_eq!(item_def.get_trait::<Tool>().unwrap().tool_type, Identifier::new("blocky", "pickaxe"));
+        assert_eq!(item_def.get_trait::<Armor>().unwrap().slot, ArmorSlot::Head);
+        assert!(!item_def.has_trait::<Fuel>());
+
+        assert_eq!(item_def.item_traits[0].get_name(), "Durability");
+    }
+
+    #[test]
+    fn unknown_traits_are_named_in_the_error() {
+        let err = parse(&ItemTraitTypes::with_builtins(), r#"(
+            id: "blocky:stick",
+            stack_size: 64,
+            item_traits: [
+                Magic(power: 9000.0),
+            ],
+        )"#).unwrap_err();
+
+        let message = err.to_string();
+
+        assert!(message.contains("unknown item trait `Magic`"), "{message}");
+        assert!(message.contains("Durability"), "{message}");
+    }
+
+    #[test]
+    fn traits_survive_a_round_trip() {
+        let trait_types = ItemTraitTypes::with_builtins();
+        let options = ron::Options::default().with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES);
+
+        let item_trait = options
+            .from_str_seed("Tool(base_damage: 2.5, tool_type: \"shovel\", tier: 3)", ItemTraitSeed(&trait_types))
+            .unwrap();
+
+        let written = ron::to_string(&item_trait).unwrap();
+        let read_back = ron::Options::default().from_str_seed(&written, ItemTraitSeed(&trait_types)).unwrap();
+        let tool = read_back.downcast_ref::<Tool>().unwrap();
+
+        assert_eq!(written, "Tool((base_damage:2.5,tool_type:\"blocky:shovel\",tier:3))");
+        assert_eq!(tool.base_damage, 2.5);
+        assert_eq!(tool.tool_type, Identifier::new("blocky", "shovel"));
+        assert_eq!(tool.tier, 3);
+    }
+
+    #[derive(Component, Debug, Clone, Deserialize, Serialize)]
+    struct Bouncy {
+        height: f32,
+    }
+
+    impl ItemTrait for Bouncy {
+        fn name() -> &'static str { "Bouncy" }
+    }
+
+    #[test]
+    fn registered_traits_can_be_read_and_written() {
+        let trait_types = ItemTraitTypes::with_builtins();
+        trait_types.register::<Bouncy>();
+
+        let item_def = parse(&trait_types, r#"(
+            id: "blocky:ball",
+            stack_size: 16,
+            item_traits: [
+                Bouncy(height: 3.0),
+                Fuel(burn_time: 5.0),
+            ],
+        )"#).unwrap();
+
+        assert_eq!(item_def.get_trait::<Bouncy>().unwrap().height, 3.);
+
+        let written = ron::to_string(&item_def.item_traits).unwrap();
+
+        assert_eq!(written, "[Bouncy((height:3.0)),Fuel((burn_time:5.0))]");
+
+        // other registries never see it
+        assert!(!ItemTraitTypes::with_builtins().get_names().contains(&"Bouncy"));
+    }
+}
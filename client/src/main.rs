@@ -2,6 +2,7 @@ use std::time::Instant;
 
 use bevy::{
     prelude::*,
+    asset::{AssetServerSettings, AssetIoError},
     diagnostic::FrameTimeDiagnosticsPlugin,
     render::{settings::WgpuSettings, render_resource::WgpuFeatures, texture::ImageSettings},
     pbr::wireframe::{WireframePlugin, WireframeConfig, Wireframe}
@@ -11,7 +12,10 @@ use bevy_atmosphere::prelude::*;
 use bevy_egui::EguiPlugin;
 use chunk_manager::{spawn_ex_chunk_tasks, handle_chunk_tasks};
 use chunky::{Chunk, CHUNK_SIZE};
-use identifier::Identifier;
+use identifier::{Identifier, IdValidationError};
+use inventory::{Inventory, InventoryPlugin};
+use item_trait::ItemTraitsPlugin;
+use mining::{BlockBreaking, MiningPlugin};
 use iyes_loopless::prelude::*;
 use player_cam::*;
 use registry::*;
@@ -26,23 +30,50 @@ pub mod block;
 pub mod registry;
 pub mod identifier;
 pub mod item;
+pub mod item_trait;
 pub mod procedural;
 pub mod texture_atlas;
 pub mod ui;
 pub mod custom_material;
 pub mod chunk_manager;
+pub mod data_loader;
+pub mod tags;
+pub mod model;
+pub mod texture_animation;
+pub mod tint;
+pub mod connected_texture;
+pub mod block_entity;
+pub mod inventory;
+pub mod item_icon;
+pub mod mining;
 
 /// Returned when there is an error reading a file or directory
 #[derive(thiserror::Error, Debug)]
 pub enum BlockyPathError {
     #[error("An error occurred while reading directory {0}: {1}")]
-    DirectoryReadError(String, std::io::Error),
+    DirectoryReadError(String, AssetIoError),
     
     #[error("An error occurred while reading file in path {0}: {1}")]
     PathReadError(String, std::io::Error),
     
     #[error("An error occurred while parsing ron file {0}: {1}")]
     FileParseError(String, ron::error::Error),
+
+    #[error("Asset {0} failed to load")]
+    AssetLoadError(String),
+
+    #[error("File {0} is not inside a known data folder")]
+    UnknownDataFile(String),
+
+    #[error("File {0} contains an invalid ID: {1}")]
+    InvalidId(String, IdValidationError),
+
+    #[error("File {path} defines `{id}`, but is inside the data folder for namespace `{namespace}`")]
+    NamespaceMismatch {
+        path: String,
+        id: String,
+        namespace: String,
+    },
 }
 
 #[derive(Debug)]
@@ -58,6 +89,8 @@ pub struct GameVersion {
 pub enum AppState {
     LoadResources,
     Registry,
+    /// Some definitions couldn't be loaded; shows the `RegistryLoadReport`
+    LoadError,
     Finished,
 }
 
@@ -85,6 +118,11 @@ fn main() {
         title: String::from("Blocky"),
         ..Default::default()
       })
+      .insert_resource(AssetServerSettings {
+        // reload block and item definitions when they change on disk
+        watch_for_changes: true,
+        ..default()
+      })
       .insert_resource(GameVersion::default())
       .insert_resource(WorldGenSettings::default())
       .add_loopless_state(AppState::LoadResources)
@@ -96,7 +134,10 @@ fn main() {
       .add_plugin(EguiPlugin)
       .add_plugin(TextureAtlasesPlugin)
       .add_plugin(UIPlugin)
+      .add_plugin(ItemTraitsPlugin)
       .add_plugin(RegistryPlugin)
+      .add_plugin(InventoryPlugin)
+      .add_plugin(MiningPlugin)
       .add_startup_system(spawn_player)
       //.add_startup_system(init_setup)
       .add_exit_system_set(
@@ -114,6 +155,7 @@ fn main() {
           .run_in_state(AppState::Finished)
           .with_system(toggle_wireframe)
           .with_system(handle_chunk_tasks)
+          .with_system(block_entity::burn_furnace_fuel)
           .into()
       )
       //.add_system(ui_world_gen)
@@ -181,6 +223,8 @@ pub fn spawn_player(mut commands: Commands) {
             friction: 0.6,
             ..Default::default()
     })
+    .insert(Inventory::default())
+    .insert(BlockBreaking::default())
     .insert(UiCameraConfig { show_ui: true })
     .insert(AtmosphereCamera(None));
 }
@@ -205,15 +249,19 @@ pub fn world_setup(
     //println!("Took {}ms to mesh and place 17^2 chunks!", gen_start.elapsed().as_millis());
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn gen_chunks(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     our_atlases: Res<TextureAtlasHandles>,
     texture_atlases: Res<Assets<TextureAtlas>>,
+    block_registry: Res<BlockRegistry>,
+    state_registry: Res<BlockStateRegistry>,
+    colormaps: Res<tint::Colormaps>,
 ) {
     
-    let block = get_block_from_registry(&Identifier::new("blocky", "grass_block")).unwrap();
+    let block = block_registry.get(&Identifier::new("blocky", "grass_block")).unwrap().get_default_state();
 
     //let mut rng = rand::thread_rng();
 
@@ -235,15 +283,19 @@ pub fn gen_chunks(
 
                 let chunk_noise_map = genner.gen_noise_map(chunk_pos);
 
+                chunk.set_climate_map(genner.gen_climate_map(chunk_pos));
+
                 for z in 0..CHUNK_SIZE as i32 {
                     for x in 0..CHUNK_SIZE as i32 {
                         let y_pos = chunk_noise_map[z as usize * CHUNK_SIZE + x as usize];
                         
-                        chunk.add_block(
+                        chunk.place_block(
+                            &block_registry,
+                            &state_registry,
                             x as usize,
                             (y_pos) as usize,
                             z as usize,
-                            Some(block.clone())
+                            Some(block)
                         );
                     }
                 }
@@ -252,7 +304,7 @@ pub fn gen_chunks(
 
                 //let mesh_start = Instant::now();
 
-                let mesh = build_chunk_mesh(&chunk);
+                let mesh = build_chunk_mesh(&chunk, &block_registry, &state_registry, &colormaps);
                 let mesh_handle = meshes.add(mesh);
 
                 //println!("Took {}ms to build mesh!", mesh_start.elapsed().as_millis());
@@ -264,6 +316,8 @@ pub fn gen_chunks(
                     material: materials.add(
                         StandardMaterial {
                             base_color_texture: Some(texture_atlas.texture.clone()),
+                            // cut out the see-through pixels of overlays, glass and leaves
+                            alpha_mode: AlphaMode::Mask(0.5),
                             ..Default::default()
                     }),
                     ..Default::default()
@@ -0,0 +1,427 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    AppState,
+    block::Block,
+    chunky::{Chunk, ChunkMesh, CHUNK_SIZE, build_chunk_mesh},
+    inventory::{Inventory, InventoryScreen, InventorySlot},
+    item::ItemStack,
+    item_trait::{Durability, Tool},
+    registry::{BlockRegistry, BlockStateRegistry, ItemRegistry},
+    tags::TagRegistry,
+    tint::Colormaps,
+};
+
+/// How far away in blocks a player can mine
+pub const REACH: f32 = 5.;
+
+/// Break time per point of hardness, for blocks that drop their item
+const HARVEST_TIME: f32 = 1.5;
+/// Break time per point of hardness, for blocks that drop nothing with the held item
+const NO_HARVEST_TIME: f32 = 5.;
+
+/// Returns `true` if `tool` is made for mining `block`,
+/// meaning the block is in the tool type's `mineable_<name>` tag
+pub fn is_effective_tool(block: &Block, tool: &Tool, tag_registry: &TagRegistry) -> bool {
+    tool.get_mineable_tag()
+        .is_some_and(|tag| tag_registry.block_has_tag(block.get_id(), tag))
+}
+
+/// Returns `true` if `block` drops its item when mined with `tool`, or by hand if `tool` is `None`.<br>
+/// Blocks with a required tier need an effective tool of at least that tier
+pub fn can_harvest(block: &Block, tool: Option<&Tool>, tag_registry: &TagRegistry) -> bool {
+    match block.get_required_tier() {
+        Some(required_tier) => tool.is_some_and(|tool|
+            tool.tier >= required_tier && is_effective_tool(block, tool, tag_registry)
+        ),
+        None => true
+    }
+}
+
+/// Seconds it takes to mine `block` with `tool`, or by hand if `tool` is `None`.<br>
+/// Effective tools divide the time by their mining speed.<br>
+/// Returns `None` if the block can't be mined
+pub fn get_break_time(block: &Block, tool: Option<&Tool>, tag_registry: &TagRegistry) -> Option<f32> {
+    if block.is_unbreakable() {
+        return None;
+    }
+
+    let speed = tool
+        .filter(|tool| is_effective_tool(block, tool, tag_registry))
+        .map_or(1., Tool::get_mining_speed);
+
+    let time_per_hardness = if can_harvest(block, tool, tag_registry) {
+        HARVEST_TIME
+    } else {
+        NO_HARVEST_TIME
+    };
+
+    Some(block.get_hardness() * time_per_hardness / speed)
+}
+
+/// Returns `true` if mining `block` with `tool` costs it durability.<br>
+/// Only effective tools wear out, and only on blocks that take time to mine
+pub fn wears_tool(block: &Block, tool: Option<&Tool>, tag_registry: &TagRegistry) -> bool {
+    block.get_hardness() > 0. &&
+    tool.is_some_and(|tool| is_effective_tool(block, tool, tag_registry))
+}
+
+/// A block in a loaded chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MiningTarget {
+    pub chunk: Entity,
+    pub pos: [usize; 3],
+}
+
+/// The block a player is mining, and how far along they are
+#[derive(Component, Debug, Clone, Default)]
+pub struct BlockBreaking {
+    pub target: Option<MiningTarget>,
+    /// From 0 to 1, where 1 breaks the block
+    pub progress: f32,
+}
+
+impl BlockBreaking {
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.progress = 0.;
+    }
+}
+
+/// Walks the blocks of `chunk` along a ray, returning the first one hit and its distance.<br>
+/// `origin` and `direction` are in the chunk's block space, where the block at
+/// `(x, y, z)` covers `x..x + 1` and so on
+pub fn raycast_chunk(chunk: &Chunk, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<([usize; 3], f32)> {
+    let size = CHUNK_SIZE as f32;
+    let origin = origin.to_array();
+    let direction = direction.to_array();
+
+    // clip the ray to the chunk's bounds
+    let mut t_enter = 0_f32;
+    let mut t_exit = max_distance;
+
+    for axis in 0..3 {
+        if direction[axis] == 0. {
+            if origin[axis] < 0. || origin[axis] >= size {
+                return None;
+            }
+        } else {
+            let t_near = -origin[axis] / direction[axis];
+            let t_far = (size - origin[axis]) / direction[axis];
+
+            t_enter = t_enter.max(t_near.min(t_far));
+            t_exit = t_exit.min(t_near.max(t_far));
+        }
+    }
+
+    if t_enter > t_exit {
+        return None;
+    }
+
+    let mut voxel = [0_i32; 3];
+    let mut step = [0_i32; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        let start = origin[axis] + direction[axis] * t_enter;
+        voxel[axis] = (start.floor() as i32).clamp(0, CHUNK_SIZE as i32 - 1);
+
+        if direction[axis] > 0. {
+            step[axis] = 1;
+            t_delta[axis] = 1. / direction[axis];
+            t_max[axis] = (voxel[axis] as f32 + 1. - origin[axis]) / direction[axis];
+        } else if direction[axis] < 0. {
+            step[axis] = -1;
+            t_delta[axis] = -1. / direction[axis];
+            t_max[axis] = (voxel[axis] as f32 - origin[axis]) / direction[axis];
+        }
+    }
+
+    let mut distance = t_enter;
+
+    loop {
+        let [x, y, z] = voxel.map(|coord| coord as usize);
+
+        if chunk.has_block_at(x, y, z) {
+            return Some(([x, y, z], distance));
+        }
+
+        // step into the next block along whichever side the ray crosses first
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] { 0 } else { 2 }
+        } else if t_max[1] < t_max[2] {
+            1
+        } else {
+            2
+        };
+
+        distance = t_max[axis];
+        voxel[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        if distance > t_exit || !(0..CHUNK_SIZE as i32).contains(&voxel[axis]) {
+            return None;
+        }
+    }
+}
+
+pub struct MiningPlugin;
+
+impl Plugin for MiningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(mine_blocks.run_in_state(AppState::Finished).after("inventory"));
+    }
+}
+
+/// Mines the block a player is looking at while the left mouse button is held.<br>
+/// Broken blocks drop their item into the player's inventory if they can be harvested,
+/// and the held item loses a point of durability
+#[allow(clippy::too_many_arguments)]
+pub fn mine_blocks(
+    time: Res<Time>,
+    mouse: Res<Input<MouseButton>>,
+    screen: Res<InventoryScreen>,
+    block_registry: Res<BlockRegistry>,
+    state_registry: Res<BlockStateRegistry>,
+    item_registry: Res<ItemRegistry>,
+    tag_registry: Res<TagRegistry>,
+    colormaps: Res<Colormaps>,
+    mut players: Query<(&GlobalTransform, &mut Inventory, &mut BlockBreaking)>,
+    mut chunks: Query<(Entity, &GlobalTransform, &mut Chunk, &ChunkMesh)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (player_transform, mut inventory, mut breaking) in &mut players {
+        if screen.open || !mouse.pressed(MouseButton::Left) {
+            breaking.reset();
+            continue;
+        }
+
+        let origin = player_transform.translation();
+        let direction = player_transform.forward();
+
+        let target = chunks.iter()
+            .filter_map(|(entity, chunk_transform, chunk, _)| {
+                // blocks are centered on their position, so shift by half a block
+                let to_chunk = chunk_transform.affine().inverse();
+                let offset = chunk.get_chunk_pos() * CHUNK_SIZE as f32 - Vec3::splat(0.5);
+
+                raycast_chunk(
+                    chunk,
+                    to_chunk.transform_point3(origin) - offset,
+                    to_chunk.transform_vector3(direction),
+                    REACH
+                ).map(|(pos, distance)| (MiningTarget { chunk: entity, pos }, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(target, _)| target);
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                breaking.reset();
+                continue;
+            }
+        };
+
+        if breaking.target != Some(target) {
+            breaking.target = Some(target);
+            breaking.progress = 0.;
+        }
+
+        let (_, _, mut chunk, chunk_mesh) = match chunks.get_mut(target.chunk) {
+            Ok(chunk) => chunk,
+            Err(_) => continue
+        };
+
+        let [x, y, z] = target.pos;
+
+        let block = match chunk.get_block(&state_registry, x, y, z)
+            .and_then(|state| block_registry.get_by_raw_id(state.get_block()))
+        {
+            Some(block) => block,
+            None => continue
+        };
+
+        let held = inventory.get_selected()
+            .and_then(|stack| item_registry.get(stack.item.get()).map(|item_def| (stack, item_def)));
+
+        // a worn out tool mines like a bare hand
+        let tool = held
+            .filter(|(stack, _)| !stack.durability.is_some_and(|durability| durability <= 0.))
+            .and_then(|(_, item_def)| item_def.get_trait::<Tool>());
+
+        let break_time = match get_break_time(block, tool, &tag_registry) {
+            Some(break_time) => break_time,
+            None => continue
+        };
+
+        breaking.progress += if break_time > 0. { time.delta_seconds() / break_time } else { 1. };
+
+        if breaking.progress < 1. {
+            continue;
+        }
+
+        let harvested = can_harvest(block, tool, &tag_registry);
+        let wears_tool = wears_tool(block, tool, &tag_registry);
+        let drop = item_registry.get(&block.get_identifier())
+            .filter(|_| harvested)
+            .map(|item_def| ItemStack::new(item_def, block.get_id(), 1));
+
+        let break_on_empty = held
+            .and_then(|(_, item_def)| item_def.get_trait::<Durability>())
+            .is_some_and(|durability| durability.break_on_empty);
+
+        if !chunk.place_block(&block_registry, &state_registry, x, y, z, None) {
+            continue;
+        }
+
+        if let Some(mesh) = meshes.get_mut(&chunk_mesh.0) {
+            *mesh = build_chunk_mesh(&chunk, &block_registry, &state_registry, &colormaps);
+        }
+
+        breaking.reset();
+
+        if wears_tool {
+            let worn_out = inventory.get_selected_mut()
+                .and_then(|stack| stack.durability.as_mut())
+                .is_some_and(|durability| {
+                    *durability = (*durability - 1.).max(0.);
+                    *durability <= 0.
+                });
+
+            if worn_out && break_on_empty {
+                let selected = inventory.selected;
+                inventory.remove(InventorySlot::Hotbar(selected), 1);
+            }
+        }
+
+        // there are no dropped item entities yet, so whatever doesn't fit is lost
+        if let Some(drop) = drop {
+            inventory.insert(drop, &item_registry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::{HashMap, HashSet};
+
+    use super::*;
+
+    use crate::identifier::Identifier;
+
+    fn block(name: &str, hardness: f32, required_tier: Option<u32>) -> Block {
+        Block {
+            id: Identifier::new("blocky", name).intern(),
+            properties: Vec::new(),
+            first_state: 0,
+            hardness,
+            required_tier,
+            blast_resistance: 0.,
+            opaque: true,
+            transparent: false,
+            luminance: 0,
+            collidable: true,
+            replaceable: false,
+            block_entity: None,
+        }
+    }
+
+    fn pickaxe(tier: u32) -> Tool {
+        Tool {
+            base_damage: 1.,
+            tool_type: Identifier::new("blocky", "pickaxe"),
+            tier,
+        }
+    }
+
+    fn shovel() -> Tool {
+        Tool {
+            base_damage: 1.,
+            tool_type: Identifier::new("blocky", "shovel"),
+            tier: 0,
+        }
+    }
+
+    fn tags() -> TagRegistry {
+        let tag = Identifier::new("blocky", "mineable_pickaxe");
+        let blocks = ["mining_stone", "mining_ore"].into_iter()
+            .map(|name| Identifier::new("blocky", name))
+            .collect::<HashSet<_>>();
+
+        TagRegistry::new(HashMap::from([(tag, blocks)]), HashMap::new())
+    }
+
+    #[test]
+    fn effective_tools_mine_faster() {
+        let tags = tags();
+        let stone = block("mining_stone", 2., None);
+
+        let by_hand = get_break_time(&stone, None, &tags).unwrap();
+        let wrong_tool = get_break_time(&stone, Some(&shovel()), &tags).unwrap();
+        let wood = get_break_time(&stone, Some(&pickaxe(0)), &tags).unwrap();
+        let iron = get_break_time(&stone, Some(&pickaxe(2)), &tags).unwrap();
+
+        assert_eq!(by_hand, 3.);
+        assert_eq!(wrong_tool, by_hand);
+        assert!(wood < by_hand);
+        assert!(iron < wood);
+    }
+
+    #[test]
+    fn required_tier_limits_drops() {
+        let tags = tags();
+        let ore = block("mining_ore", 3., Some(1));
+
+        assert!(!can_harvest(&ore, None, &tags));
+        assert!(!can_harvest(&ore, Some(&shovel()), &tags));
+        assert!(!can_harvest(&ore, Some(&pickaxe(0)), &tags));
+        assert!(can_harvest(&ore, Some(&pickaxe(1)), &tags));
+
+        // blocks that drop nothing take longer
+        assert_eq!(get_break_time(&ore, None, &tags), Some(15.));
+    }
+
+    #[test]
+    fn only_effective_tools_wear_out() {
+        let tags = tags();
+        let stone = block("mining_stone", 2., None);
+        let dirt = block("mining_dirt", 0.5, None);
+        // in the pickaxe's tag, but broken instantly
+        let soft_stone = block("mining_stone", 0., None);
+
+        assert!(wears_tool(&stone, Some(&pickaxe(0)), &tags));
+        assert!(!wears_tool(&stone, Some(&shovel()), &tags));
+        assert!(!wears_tool(&dirt, Some(&pickaxe(0)), &tags));
+        assert!(!wears_tool(&soft_stone, Some(&pickaxe(0)), &tags));
+        assert!(!wears_tool(&stone, None, &tags));
+    }
+
+    #[test]
+    fn unbreakable_blocks_never_break() {
+        let bedrock = block("mining_bedrock", -1., None);
+
+        assert_eq!(get_break_time(&bedrock, Some(&pickaxe(4)), &tags()), None);
+    }
+
+    #[test]
+    fn raycast_hits_first_block() {
+        let (block_registry, state_registry) = (BlockRegistry::default(), BlockStateRegistry::default());
+
+        let mut chunk = Chunk::new(Vec3::ZERO);
+        chunk.place_block(&block_registry, &state_registry, 5, 2, 3, Some(0));
+        chunk.place_block(&block_registry, &state_registry, 8, 2, 3, Some(0));
+
+        let hit = raycast_chunk(&chunk, Vec3::new(0.5, 2.5, 3.5), Vec3::X, REACH);
+        assert_eq!(hit, Some(([5, 2, 3], 4.5)));
+
+        // out of reach
+        assert_eq!(raycast_chunk(&chunk, Vec3::new(0.5, 2.5, 3.5), Vec3::X, 4.), None);
+
+        // starting outside the chunk
+        let hit = raycast_chunk(&chunk, Vec3::new(5.5, 2.5, -1.), Vec3::Z, REACH);
+        assert_eq!(hit, Some(([5, 2, 3], 4.)));
+    }
+}
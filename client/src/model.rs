@@ -0,0 +1,184 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::{math::{Vec2, Vec3}, reflect::TypeUuid, sprite::Rect};
+use serde::{Deserialize, Serialize};
+
+use crate::{block::{BlockFace, get_face_uvs}, identifier::Identifier};
+
+/// Width of a block in model units
+pub const MODEL_SIZE: f32 = 16.;
+
+/// A block shape made of boxes and quads, loaded from
+/// `assets/data/<namespace>/models/<name>.ron`.<br>
+/// Positions are in pixels, from `(0, 0, 0)` to `(16, 16, 16)`
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "6f0f1b0e-4f54-4c3b-9a55-2f3d8e7c1a90"]
+pub struct BlockModelDefinition {
+    /// Sides of the block this model covers completely.<br>
+    /// Only these sides hide the faces of neighbouring blocks
+    #[serde(default)]
+    pub full_faces: Vec<BlockFace>,
+
+    pub elements: Vec<ModelElement>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum ModelElement {
+    /// An axis-aligned box from `from` to `to`.<br>
+    /// Only the listed faces are drawn
+    Box {
+        from: [f32; 3],
+        to: [f32; 3],
+        faces: BTreeMap<BlockFace, ModelFace>,
+    },
+
+    /// A single quad, with its vertices in counter-clockwise order
+    /// when seen from the front, starting at the top left of the texture
+    Quad {
+        vertices: [[f32; 3]; 4],
+        face: ModelFace,
+    },
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModelFace {
+    /// Texture drawn on this face.<br>
+    /// `#top`, `#front`, ... use the block's texture for that side,
+    /// and leaving it empty uses the block's texture for the side this face points to
+    #[serde(default)]
+    pub texture: String,
+
+    /// This face is hidden when the neighbour on this side
+    /// of the block has a full face touching it
+    #[serde(default)]
+    pub cull: Option<BlockFace>,
+}
+
+/// A model face ready to be added to a chunk mesh
+#[derive(Debug, Clone)]
+pub struct BakedQuad {
+    /// Vertex positions relative to the block's center, and their normals
+    pub vertices: [([f32; 3], [f32; 3]); 4],
+    pub uvs: [[f32; 2]; 4],
+    pub cull: Option<BlockFace>,
+
+    /// Face of the block this quad is tinted like
+    pub face: BlockFace,
+}
+
+/// Returns the ID of the model stored at `path`, relative to the `assets` folder.<br>
+/// `data/blocky/models/slab.ron` is the model `blocky:slab`
+pub fn model_id_from_path(path: &Path) -> Option<Identifier> {
+    let mut components = path.with_extension("").components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .into_iter();
+
+    if components.next()? != "data" {
+        return None;
+    }
+
+    let namespace = components.next()?;
+
+    if components.next()? != "models" {
+        return None;
+    }
+
+    let name = components.collect::<Vec<String>>().join("/");
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(Identifier::new(&namespace, &name))
+    }
+}
+
+impl BlockModelDefinition {
+    /// Bitmask of the `VoxelCullCode`s of every full face
+    pub fn get_full_faces(&self) -> u8 {
+        self.full_faces.iter().fold(0, |mask, face| mask | face.cull_code())
+    }
+
+    /// Turns every element into quads, using `get_texture` to
+    /// look up the atlas coordinates of the texture on each face
+    pub fn bake(&self, mut get_texture: impl FnMut(&ModelFace, BlockFace) -> Rect) -> Vec<BakedQuad> {
+        let mut quads = Vec::new();
+
+        for element in &self.elements {
+            match element {
+                ModelElement::Box { from, to, faces } => {
+                    let from = to_block_space(*from);
+                    let to = to_block_space(*to);
+
+                    for (block_face, model_face) in faces {
+                        let tex_coords = get_texture(model_face, *block_face);
+
+                        quads.push(bake_box_face(*block_face, from, to, &tex_coords, model_face.cull));
+                    }
+                },
+                ModelElement::Quad { vertices, face } => {
+                    let tex_coords = get_texture(face, BlockFace::Front);
+
+                    quads.push(bake_quad(vertices.map(to_block_space), &tex_coords, face.cull));
+                }
+            }
+        }
+
+        quads
+    }
+}
+
+/// Converts a position in model units to one relative to the block's center
+fn to_block_space(position: [f32; 3]) -> [f32; 3] {
+    position.map(|axis| axis / MODEL_SIZE - 0.5)
+}
+
+/// Shrinks the cube face `block_face` down to the box from `from` to `to`,
+/// cropping its texture to match
+fn bake_box_face(block_face: BlockFace, from: [f32; 3], to: [f32; 3], tex_coords: &Rect, cull: Option<BlockFace>) -> BakedQuad {
+    let cube_vertices = block_face.get_vertices();
+    let cube_uvs = get_face_uvs(block_face, tex_coords).map(Vec2::from);
+
+    // the cube's corners are all at +-0.5, so they pick the matching side of the box
+    let vertices = cube_vertices.map(|(position, normal)| {
+        let position = [0, 1, 2].map(|axis|
+            if position[axis] < 0. { from[axis] } else { to[axis] }
+        );
+
+        (position, normal)
+    });
+
+    // the texture is cropped like it was projected onto the full cube face
+    let origin = Vec3::from(cube_vertices[0].0);
+    let edge_u = Vec3::from(cube_vertices[1].0) - origin;
+    let edge_v = Vec3::from(cube_vertices[3].0) - origin;
+
+    let uvs = vertices.map(|(position, _)| {
+        let offset = Vec3::from(position) - origin;
+
+        let uv = cube_uvs[0]
+            + offset.dot(edge_u) * (cube_uvs[1] - cube_uvs[0])
+            + offset.dot(edge_v) * (cube_uvs[3] - cube_uvs[0]);
+
+        uv.to_array()
+    });
+
+    BakedQuad { vertices, uvs, cull, face: block_face }
+}
+
+fn bake_quad(positions: [[f32; 3]; 4], tex_coords: &Rect, cull: Option<BlockFace>) -> BakedQuad {
+    let [first, second, third, _] = positions.map(Vec3::from);
+    let normal = (second - first).cross(third - first).normalize_or_zero().to_array();
+
+    BakedQuad {
+        vertices: positions.map(|position| (position, normal)),
+        uvs: [
+            [tex_coords.min.x, tex_coords.min.y],
+            [tex_coords.max.x, tex_coords.min.y],
+            [tex_coords.max.x, tex_coords.max.y],
+            [tex_coords.min.x, tex_coords.max.y],
+        ],
+        cull,
+        face: BlockFace::Front,
+    }
+}
@@ -2,6 +2,22 @@ use bevy::{math::Vec3, prelude::*};
 use noise::{NoiseFn, OpenSimplex, Seedable};
 use rand::{SeedableRng, Rng};
 
+/// How hot and wet it is somewhere, both from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+impl Default for Climate {
+    fn default() -> Self {
+        Self { temperature: 0.8, humidity: 0.4 }
+    }
+}
+
+/// Blocks across a single patch of climate noise
+const CLIMATE_SCALE: f64 = 256.;
+
 #[derive(Clone, Copy)]
 pub struct ProcGen {
     seed: u32,
@@ -82,6 +98,29 @@ impl ProcGen {
         height_map
     }
 
+    /// Climate of every column in the map at `map_position`
+    pub fn gen_climate_map(&self, map_position: Vec3) -> Vec<Climate> {
+        let mut climate_map = vec![Climate::default(); self.map_size.pow(2)];
+
+        for z in 0..self.map_size {
+            for x in 0..self.map_size {
+                let block_x = (x as f32 + map_position.x * self.map_size as f32) as f64 / CLIMATE_SCALE;
+                let block_z = (z as f32 + map_position.z * self.map_size as f32) as f64 / CLIMATE_SCALE;
+
+                // offset humidity so it doesn't follow temperature
+                let temperature = self.simplex.get([block_x, block_z]);
+                let humidity = self.simplex.get([block_x + 1000., block_z + 1000.]);
+
+                climate_map[z * self.map_size + x] = Climate {
+                    temperature: ((temperature + 1.) / 2.) as f32,
+                    humidity: ((humidity + 1.) / 2.) as f32,
+                };
+            }
+        }
+
+        climate_map
+    }
+
     pub fn gen_noise_map_old(
         &self,
         map_position: Vec3,
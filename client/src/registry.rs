@@ -1,258 +1,1457 @@
-use std::sync::Mutex;
+use std::{sync::Arc, path::{Path, PathBuf}, str::FromStr};
 
-use bevy::{sprite::{TextureAtlas, Rect}, prelude::{Res, AssetServer, Plugin, Commands}};
-use hashbrown::HashMap;
-use iyes_loopless::{prelude::AppLooplessStateExt, state::NextState};
-use lazy_static::lazy_static;
+use bevy::{
+    asset::{Asset, AssetIo, LoadState},
+    sprite::Rect,
+    prelude::{Res, ResMut, AssetServer, Plugin, Commands, Handle, Assets, AddAsset, EventReader, AssetEvent, Query, Mesh}
+};
+use hashbrown::{HashMap, HashSet};
+use iyes_loopless::{prelude::*, state::NextState};
 
-use crate::{item::ItemDefinition, identifier::Identifier, BlockyPathError, block::{Block, BlockDefinition, BlockFace}, texture_atlas::atlas_coords_fix, AppState};
+use crate::{
+    item::{ItemDefinition, ItemValidationError},
+    identifier::Identifier,
+    BlockyPathError,
+    block::{Block, BlockDefinition, BlockFace, BlockState, FaceLayer, RandomFace, VoxelCullCode, expand_states, resolve_block_definitions, MAX_LUMINANCE, DEFAULT_HARDNESS},
+    chunky::{Chunk, ChunkMesh, build_chunk_mesh},
+    data_loader::{DefinitionLoader, DefinitionKind, namespace_from_path},
+    tags::{TagDefinition, TagRegistry, tag_id_from_path, resolve_tags},
+    model::{BlockModelDefinition, model_id_from_path},
+    tint::Colormaps,
+    AppState
+};
 
-lazy_static! {
-    static ref ITEM_REGISTRY: Mutex<HashMap<String, ItemDefinition>> = Mutex::new(HashMap::new());
-    static ref BLOCK_REGISTRY: Mutex<HashMap<String, Block>> = Mutex::new(HashMap::new());
-    static ref BLOCK_TEXTURE_COORDS: Mutex<HashMap<String, Rect>> = Mutex::new(HashMap::new());
+/// Folder containing a data pack for every namespace, relative to the `assets` folder
+/// (`assets/data/<namespace>/{blocks,items,tags}`)
+pub const DATA_PATH: &str = "data";
+
+/// Numeric ID assigned to an entry when it is registered.<br>
+/// IDs are dense and handed out in registration order, starting at 0
+pub type RawId = u16;
+
+/// Returned when an entry can't be added to a registry
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum RegistryError {
+    #[error("Registry is frozen; `{0}` can't be registered")]
+    Frozen(String),
+
+    #[error("Registry is full; `{0}` can't be registered")]
+    Full(String),
+}
+
+#[derive(Clone)]
+struct RegistryEntries<T> {
+    entries: Vec<T>,
+    raw_ids: HashMap<Identifier, RawId>,
+}
+
+/// Stores registered values by `Identifier` and by numeric runtime ID.
+///
+/// Entries live behind an `Arc`, so cloning a registry is cheap and
+/// lets async tasks (chunk meshing, etc) read it without locking
+pub struct Registry<T> {
+    inner: Arc<RegistryEntries<T>>,
+    frozen: bool,
+}
+
+pub type BlockRegistry = Registry<Block>;
+pub type ItemRegistry = Registry<ItemDefinition>;
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RegistryEntries {
+                entries: Vec::new(),
+                raw_ids: HashMap::new(),
+            }),
+            frozen: false,
+        }
+    }
+}
+
+impl<T> Clone for Registry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            frozen: self.frozen,
+        }
+    }
+}
+
+impl<T: Clone> Registry<T> {
+    /// Adds `value` to the registry under `id` and returns its runtime ID.<br>
+    /// If `id` is already registered, the old value is overwritten and keeps its runtime ID
+    pub fn register(&mut self, id: &Identifier, value: T) -> Result<RawId, RegistryError> {
+        self.check_register(id)?;
+
+        if let Some(raw_id) = self.get_raw_id(id) {
+            Arc::make_mut(&mut self.inner).entries[raw_id as usize] = value;
+
+            return Ok(raw_id);
+        }
+
+        let inner = Arc::make_mut(&mut self.inner);
+
+        let raw_id = inner.entries.len() as RawId;
+
+        inner.entries.push(value);
+        inner.raw_ids.insert(id.clone(), raw_id);
+
+        Ok(raw_id)
+    }
+
+    /// Returns the error `register` would give for `id`, without registering anything
+    pub fn check_register(&self, id: &Identifier) -> Result<(), RegistryError> {
+        if self.frozen {
+            return Err(RegistryError::Frozen(id.to_string()));
+        }
+
+        if !self.contains(id) && self.len() > RawId::MAX as usize {
+            return Err(RegistryError::Full(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Overwrites an entry that is already registered, even if the registry is frozen.<br>
+    /// Runtime IDs never change, so returns `None` instead of adding new entries
+    pub fn replace(&mut self, id: &Identifier, value: T) -> Option<RawId> {
+        let raw_id = self.get_raw_id(id)?;
+
+        // tasks still holding the old entries keep their copy
+        Arc::make_mut(&mut self.inner).entries[raw_id as usize] = value;
+
+        Some(raw_id)
+    }
+}
+
+impl<T> Registry<T> {
+    /// Stops any more entries from being registered
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn is_frozen(&self) -> bool { self.frozen }
+
+    pub fn contains(&self, id: &Identifier) -> bool {
+        self.inner.raw_ids.contains_key(id)
+    }
+
+    pub fn get(&self, id: &Identifier) -> Option<&T> {
+        self.get_raw_id(id).and_then(|raw_id| self.get_by_raw_id(raw_id))
+    }
+
+    pub fn get_by_raw_id(&self, raw_id: RawId) -> Option<&T> {
+        self.inner.entries.get(raw_id as usize)
+    }
+
+    pub fn get_raw_id(&self, id: &Identifier) -> Option<RawId> {
+        self.inner.raw_ids.get(id).copied()
+    }
+
+    pub fn len(&self) -> usize { self.inner.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.inner.entries.is_empty() }
+
+    /// Iterates over every entry in runtime ID order
+    pub fn iter(&self) -> impl Iterator<Item = (RawId, &T)> {
+        self.inner.entries.iter().enumerate().map(|(raw_id, value)| (raw_id as RawId, value))
+    }
+}
+
+/// Numeric ID of a block state.<br>
+/// Every state of every block has its own ID, and
+/// the states of a block are numbered consecutively
+pub type StateId = u16;
+
+/// Every registered block state, numbered by `StateId`.
+///
+/// Like `Registry`, states live behind an `Arc` so
+/// meshing tasks can read them without locking
+#[derive(Clone, Default)]
+pub struct BlockStateRegistry {
+    states: Arc<Vec<BlockState>>,
+    frozen: bool,
+}
+
+impl BlockStateRegistry {
+    /// Adds every state of `block` and returns the ID of the first one
+    pub fn register(&mut self, block: &Identifier, states: Vec<BlockState>) -> Result<StateId, RegistryError> {
+        if self.frozen {
+            return Err(RegistryError::Frozen(block.to_string()));
+        }
+
+        if self.states.len() + states.len() > StateId::MAX as usize + 1 {
+            return Err(RegistryError::Full(block.to_string()));
+        }
+
+        let first_state = self.states.len() as StateId;
+
+        Arc::make_mut(&mut self.states).extend(states);
+
+        Ok(first_state)
+    }
+
+    /// Overwrites already registered states, starting at `first_state`,
+    /// even if the registry is frozen
+    pub fn replace(&mut self, first_state: StateId, states: Vec<BlockState>) {
+        let all_states = Arc::make_mut(&mut self.states);
+
+        for (index, state) in states.into_iter().enumerate() {
+            if let Some(old_state) = all_states.get_mut(first_state as usize + index) {
+                *old_state = state;
+            }
+        }
+    }
+
+    /// Stops any more states from being registered
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn get(&self, state_id: StateId) -> Option<&BlockState> {
+        self.states.get(state_id as usize)
+    }
+
+    pub fn len(&self) -> usize { self.states.len() }
+
+    pub fn is_empty(&self) -> bool { self.states.is_empty() }
+}
+
+/// Atlas coordinates for every block texture, keyed by texture path
+pub struct BlockTextureCoords {
+    coords: HashMap<String, Rect>,
+
+    /// Coordinates of every variant of each connected texture,
+    /// in `CONNECTED_VARIANTS` order
+    connected: HashMap<String, Vec<Rect>>,
+
+    /// Coordinates of the generated missing texture
+    missing: Rect,
+}
+
+impl BlockTextureCoords {
+    pub fn new(missing: Rect) -> Self {
+        Self {
+            coords: HashMap::new(),
+            connected: HashMap::new(),
+            missing,
+        }
+    }
+
+    /// Store coordinates for a block texture
+    /// for retrieval later
+    pub fn register(&mut self, texture_path: String, coords: Rect) {
+        let texture_path = normalize_texture_path(&texture_path);
+
+        if self.coords.insert(texture_path.clone(), coords).is_none() {
+            println!("Registered atlas coords for \"{}\"", texture_path);
+        } else {
+            println!("atlas coords for \"{}\" already registered; overwriting!", texture_path);
+        }
+    }
+
+    pub fn get(&self, texture_path: &str) -> Option<Rect> {
+        self.coords.get(&normalize_texture_path(texture_path)).copied()
+    }
+
+    /// Store coordinates for every variant of a connected texture.<br>
+    /// The variant without connections is also registered as the texture itself
+    pub fn register_connected(&mut self, texture_path: String, variants: Vec<Rect>) {
+        if let Some(isolated) = variants.first() {
+            self.register(texture_path.clone(), *isolated);
+        }
+
+        self.connected.insert(normalize_texture_path(&texture_path), variants);
+    }
+
+    pub fn get_connected(&self, texture_path: &str) -> Option<&[Rect]> {
+        self.connected.get(&normalize_texture_path(texture_path)).map(|variants| variants.as_slice())
+    }
+
+    /// Coordinates of the magenta and black checkerboard texture
+    pub fn missing(&self) -> Rect { self.missing }
+}
+
+/// Atlas coordinates for every item icon, keyed by texture path
+pub struct ItemTextureCoords {
+    coords: HashMap<String, Rect>,
+
+    /// Coordinates of generated icons, keyed by item ID
+    icons: HashMap<String, Rect>,
+
+    /// Coordinates of the generated missing texture
+    missing: Rect,
+}
+
+impl ItemTextureCoords {
+    pub fn new(missing: Rect) -> Self {
+        Self {
+            coords: HashMap::new(),
+            icons: HashMap::new(),
+            missing,
+        }
+    }
+
+    pub fn register(&mut self, texture_path: String, coords: Rect) {
+        self.coords.insert(normalize_texture_path(&texture_path), coords);
+    }
+
+    /// Store coordinates for an icon generated for the item `item_id`,
+    /// used instead of the item's texture
+    pub fn register_icon(&mut self, item_id: String, coords: Rect) {
+        self.icons.insert(item_id, coords);
+    }
+
+    /// Returns the coordinates of `texture_path`, or of the missing texture if it isn't in the atlas
+    pub fn get(&self, texture_path: &str) -> Rect {
+        self.coords.get(&normalize_texture_path(texture_path)).copied().unwrap_or(self.missing)
+    }
+
+    /// Coordinates of the icon of `item_def`
+    pub fn get_icon(&self, item_def: &ItemDefinition) -> Rect {
+        if let Some(coords) = self.icons.get(&item_def.id) {
+            return *coords;
+        }
+
+        item_def.get_texture_path()
+            .map_or(self.missing, |texture_path| self.get(&texture_path))
+    }
+
+    /// Coordinates of the magenta and black checkerboard texture
+    pub fn missing(&self) -> Rect { self.missing }
+}
+
+/// Texture paths use `/` as a separator on every platform
+fn normalize_texture_path(texture_path: &str) -> String {
+    texture_path.replace('\\', "/")
+}
+
+/// A problem found while loading the data packs.<br>
+/// Definitions with problems are skipped instead of registered
+#[derive(thiserror::Error, Debug)]
+pub enum LoadProblem {
+    #[error(transparent)]
+    Path(#[from] BlockyPathError),
+
+    #[error("Block `{block}` in file {path} is missing texture `{texture}` for its {face:?} face; using the missing texture")]
+    MissingTexture {
+        path: String,
+        block: String,
+        face: BlockFace,
+        texture: String,
+    },
+
+    #[error("Tag `{tag}` in file {path} contains `{entry}`, which doesn't exist")]
+    UnknownTagEntry {
+        path: String,
+        tag: String,
+        entry: String,
+    },
+
+    #[error("Tag `{tag}` in file {path} contains itself: {cycle}")]
+    TagCycle {
+        path: String,
+        tag: String,
+        cycle: String,
+    },
+
+    #[error("Block `{block}` in file {path} inherits from `{parent}`, which doesn't exist")]
+    MissingParent {
+        path: String,
+        block: String,
+        parent: String,
+    },
+
+    #[error("Block `{block}` in file {path} inherits from itself: {cycle}")]
+    ParentCycle {
+        path: String,
+        block: String,
+        cycle: String,
+    },
+
+    #[error("Block `{block}` in file {path} is already registered with {old} states, but now has {new}; it wasn't overwritten")]
+    StateCountChanged {
+        path: String,
+        block: String,
+        old: usize,
+        new: usize,
+    },
+
+    #[error("Block `{block}` in file {path} has invalid states: {reason}")]
+    InvalidBlockState {
+        path: String,
+        block: String,
+        reason: String,
+    },
+
+    #[error("Animated texture {path} can't be used: {reason}")]
+    InvalidAnimation {
+        path: String,
+        reason: String,
+    },
+
+    #[error("Connected texture {path} can't be used: {reason}")]
+    InvalidConnectedTexture {
+        path: String,
+        reason: String,
+    },
+
+    #[error("Block `{block}` in file {path} connects its textures, but `{texture}` has no connected texture sheet; it won't connect")]
+    MissingConnectedTexture {
+        path: String,
+        block: String,
+        texture: String,
+    },
+
+    #[error("Block `{block}` in file {path} uses the model `{model}`, which doesn't exist")]
+    MissingModel {
+        path: String,
+        block: String,
+        model: String,
+    },
+
+    #[error("Block `{block}` in file {path} has an invalid value: {reason}")]
+    InvalidBlockValue {
+        path: String,
+        block: String,
+        reason: String,
+    },
+
+    #[error("Item `{item}` in file {path} is invalid: {source}")]
+    InvalidItem {
+        path: String,
+        item: String,
+        source: ItemValidationError,
+    },
+
+    #[error("Couldn't register `{id}` from file {path}: {source}")]
+    Registry {
+        path: String,
+        id: String,
+        source: RegistryError,
+    },
+}
+
+/// Every problem found while loading the data packs
+#[derive(Default)]
+pub struct RegistryLoadReport {
+    problems: Vec<LoadProblem>,
+
+    /// Problems that were worked around, so
+    /// nothing had to be skipped
+    warnings: Vec<LoadProblem>,
+}
+
+impl RegistryLoadReport {
+    pub fn add(&mut self, problem: impl Into<LoadProblem>) {
+        let problem = problem.into();
+
+        println!("[Error] {problem}");
+
+        self.problems.push(problem);
+    }
+
+    pub fn warn(&mut self, problem: impl Into<LoadProblem>) {
+        let problem = problem.into();
+
+        println!("[Warning] {problem}");
+
+        self.warnings.push(problem);
+    }
+
+    pub fn problems(&self) -> &[LoadProblem] { &self.problems }
+
+    pub fn warnings(&self) -> &[LoadProblem] { &self.warnings }
+
+    /// Returns `true` if there are no problems. Warnings are ignored
+    pub fn is_empty(&self) -> bool { self.problems.is_empty() }
+}
+
+/// Handles to every definition found in the data packs
+#[derive(Default)]
+pub struct DefinitionHandles {
+    pub blocks: Vec<Handle<BlockDefinition>>,
+    pub items: Vec<Handle<ItemDefinition>>,
+    pub block_tags: Vec<Handle<TagDefinition>>,
+    pub item_tags: Vec<Handle<TagDefinition>>,
+    pub models: Vec<Handle<BlockModelDefinition>>,
 }
 
 pub struct RegistryPlugin;
 
 impl Plugin for RegistryPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_enter_system(AppState::Registry, registry_init);
+        app.add_asset::<BlockDefinition>()
+           .add_asset::<ItemDefinition>()
+           .add_asset::<TagDefinition>()
+           .add_asset::<BlockModelDefinition>()
+           .init_asset_loader::<DefinitionLoader>()
+           .init_resource::<BlockRegistry>()
+           .init_resource::<BlockStateRegistry>()
+           .init_resource::<ItemRegistry>()
+           .init_resource::<TagRegistry>()
+           .init_resource::<DefinitionHandles>()
+           .init_resource::<RegistryLoadReport>()
+           .add_enter_system(AppState::Registry, load_definitions)
+           .add_system(registry_init.run_in_state(AppState::Registry))
+           .add_exit_system(AppState::Registry, freeze_registries)
+           .add_system_set(
+              ConditionSet::new()
+                .run_in_state(AppState::Finished)
+                .label("reload-definitions")
+                .with_system(reload_block_definitions)
+                .with_system(reload_item_definitions)
+                .into()
+           );
     }
 }
 
-pub fn registry_init(mut commands: Commands) {
-    register_items_in_dir("data/blocky/items/");
-    register_blocks_in_dir("data/blocky/blocks/");
+/// Starts loading every definition in every namespace
+pub fn load_definitions(
+    asset_server: Res<AssetServer>,
+    mut def_handles: ResMut<DefinitionHandles>,
+    mut report: ResMut<RegistryLoadReport>,
+) {
+    match find_data_namespaces(asset_server.asset_io(), DATA_PATH) {
+        Ok(namespaces) => {
+            for namespace in namespaces {
+                let def_dir = |kind: DefinitionKind| format!("{DATA_PATH}/{namespace}/{}", kind.folder_name());
+
+                let items = load_definitions_in_dir(&asset_server, &mut report, &def_dir(DefinitionKind::Item));
+                let blocks = load_definitions_in_dir(&asset_server, &mut report, &def_dir(DefinitionKind::Block));
+                let item_tags = load_definitions_in_dir(&asset_server, &mut report, &def_dir(DefinitionKind::ItemTag));
+                let block_tags = load_definitions_in_dir(&asset_server, &mut report, &def_dir(DefinitionKind::BlockTag));
+                let models = load_definitions_in_dir(&asset_server, &mut report, &def_dir(DefinitionKind::Model));
 
-    commands.insert_resource(NextState(AppState::Finished))
+                def_handles.items.extend(items);
+                def_handles.blocks.extend(blocks);
+                def_handles.item_tags.extend(item_tags);
+                def_handles.block_tags.extend(block_tags);
+                def_handles.models.extend(models);
+            }
+        },
+        Err(err) => report.add(err)
+    }
 }
 
-/// Store coordinates for a block texture
-/// for retrieval later
-pub fn register_block_texture_coords(
-    texture_path: String,
-    atlas: &TextureAtlas,
-    asset_server: &Res<AssetServer>,
+/// Registers every definition once they are all done loading,
+/// then resolves tags against the registered blocks and items.<br>
+/// Moves on to `AppState::LoadError` if anything had to be skipped
+#[allow(clippy::too_many_arguments)]
+pub fn registry_init(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    def_handles: Res<DefinitionHandles>,
+    block_defs: Res<Assets<BlockDefinition>>,
+    item_defs: Res<Assets<ItemDefinition>>,
+    tag_defs: Res<Assets<TagDefinition>>,
+    model_defs: Res<Assets<BlockModelDefinition>>,
+    mut block_registry: ResMut<BlockRegistry>,
+    mut state_registry: ResMut<BlockStateRegistry>,
+    mut item_registry: ResMut<ItemRegistry>,
+    mut tag_registry: ResMut<TagRegistry>,
+    tex_coords: Res<BlockTextureCoords>,
+    mut report: ResMut<RegistryLoadReport>,
 ) {
-    let mut tex_coords_registry = BLOCK_TEXTURE_COORDS.lock().unwrap();
+    if !definitions_done_loading(&asset_server, &def_handles.items) ||
+       !definitions_done_loading(&asset_server, &def_handles.blocks) ||
+       !definitions_done_loading(&asset_server, &def_handles.item_tags) ||
+       !definitions_done_loading(&asset_server, &def_handles.block_tags) ||
+       !definitions_done_loading(&asset_server, &def_handles.models) {
+        return;
+    }
 
-    let tex_handle = asset_server.get_handle(&texture_path);
-    let tex_index = atlas.get_texture_index(&tex_handle).unwrap();
+    for handle in &def_handles.items {
+        let file_path = get_handle_path(&asset_server, handle);
 
-    let texture_size = atlas_coords_fix(atlas.textures[tex_index], atlas.size);
+        let item_def = item_defs.get(handle)
+            .ok_or_else(|| BlockyPathError::AssetLoadError(file_path.clone()))
+            .and_then(|item_def| {
+                check_namespace(&file_path, &item_def.id)?;
 
-    if !tex_coords_registry.contains_key(&texture_path) {
-        tex_coords_registry.insert(texture_path.clone(), texture_size);
+                Ok(item_def)
+            });
 
-        println!("Registered atlas coords for \"{}\"", texture_path);
-    } else {
-        // overwrite key if it exists
-        tex_coords_registry.entry(texture_path.clone()).and_modify(|e| *e = texture_size);
+        match item_def {
+            Ok(item_def) if check_item_definition(&mut report, &file_path, item_def) => {
+                register_item(&mut item_registry, &mut report, &file_path, item_def.clone())
+            },
+            Ok(_) => (),
+            Err(err) => report.add(err)
+        }
+    }
+
+    let models = collect_model_definitions(&asset_server, &model_defs, &def_handles.models, &mut report);
+    let all_block_defs = collect_block_definitions(&asset_server, &block_defs, &def_handles.blocks, &mut report);
 
-        println!("atlas coords for \"{}\" already registered; overwriting!", texture_path);
+    // parents are registered before their children
+    for (file_path, block_def) in resolve_block_definitions(&all_block_defs, &mut report) {
+        register_block(&mut block_registry, &mut state_registry, &mut item_registry, &tex_coords, &models, &mut report, &file_path, &block_def);
     }
-}
 
-pub fn get_block_texture_coords(texture_path: String) -> Option<Rect> {
-    let tex_coords_registry = BLOCK_TEXTURE_COORDS.lock().unwrap();
+    let item_tag_defs = collect_tag_definitions(&asset_server, &tag_defs, &def_handles.item_tags, &mut report);
+    let block_tag_defs = collect_tag_definitions(&asset_server, &tag_defs, &def_handles.block_tags, &mut report);
 
-    if tex_coords_registry.contains_key(&texture_path) {
-        let registered_tex = tex_coords_registry[&texture_path].clone();
-        
-        Some(registered_tex)
+    *tag_registry = TagRegistry::new(
+        resolve_tags(&block_tag_defs, |id| block_registry.contains(id), &mut report),
+        resolve_tags(&item_tag_defs, |id| item_registry.contains(id), &mut report),
+    );
+
+    if report.is_empty() {
+        commands.insert_resource(NextState(AppState::Finished))
     } else {
-        None
+        commands.insert_resource(NextState(AppState::LoadError))
     }
 }
 
-/// Adds a block to the block registry
-pub fn register_block(
-    block_def: BlockDefinition
+/// Registries are read-only once `AppState::Registry` is left
+pub fn freeze_registries(
+    mut block_registry: ResMut<BlockRegistry>,
+    mut state_registry: ResMut<BlockStateRegistry>,
+    mut item_registry: ResMut<ItemRegistry>,
 ) {
-    let mut block_registry = BLOCK_REGISTRY.lock().unwrap();
+    block_registry.freeze();
+    state_registry.freeze();
+    item_registry.freeze();
+}
 
-    let id = match Identifier::from_str(&block_def.id) {
-        Ok(id) => Some(id),
-        Err(err) => {
-            println!("{err}");
-            None
+/// Updates blocks whose definition changed on disk, along with
+/// any block inheriting from them, and remeshes any chunk containing them
+#[allow(clippy::too_many_arguments)]
+pub fn reload_block_definitions(
+    mut block_def_events: EventReader<AssetEvent<BlockDefinition>>,
+    asset_server: Res<AssetServer>,
+    def_handles: Res<DefinitionHandles>,
+    block_defs: Res<Assets<BlockDefinition>>,
+    model_defs: Res<Assets<BlockModelDefinition>>,
+    tex_coords: Res<BlockTextureCoords>,
+    mut block_registry: ResMut<BlockRegistry>,
+    mut state_registry: ResMut<BlockStateRegistry>,
+    mut report: ResMut<RegistryLoadReport>,
+    colormaps: Res<Colormaps>,
+    chunks: Query<(&Chunk, &ChunkMesh)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let changed_ids = block_def_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => block_defs.get(handle).map(|block_def| normalize_id(&block_def.id)),
+            _ => None
+        })
+        .collect::<HashSet<String>>();
+
+    if changed_ids.is_empty() {
+        return;
+    }
+
+    let models = collect_model_definitions(&asset_server, &model_defs, &def_handles.models, &mut report);
+    let all_block_defs = collect_block_definitions(&asset_server, &block_defs, &def_handles.blocks, &mut report);
+
+    let parents = all_block_defs.iter()
+        .filter(|(_, block_def)| !block_def.parent.is_empty())
+        .map(|(_, block_def)| (normalize_id(&block_def.id), normalize_id(&block_def.parent)))
+        .collect::<HashMap<String, String>>();
+
+    // blocks that changed or inherit from a block that changed,
+    // and every block they inherit from
+    let mut affected_ids = HashSet::new();
+    let mut needed_ids = HashSet::new();
+
+    for (_, block_def) in &all_block_defs {
+        let chain = get_parent_chain(&normalize_id(&block_def.id), &parents);
+
+        if chain.iter().any(|id| changed_ids.contains(id)) {
+            affected_ids.insert(normalize_id(&block_def.id));
+            needed_ids.extend(chain);
         }
-    };
-    
-    if let Some(id) = id {
-        let top_texture_path = block_def.get_texture_for_face(BlockFace::Top);
-        let btm_texture_path = block_def.get_texture_for_face(BlockFace::Bottom);
-        let left_texture_path = block_def.get_texture_for_face(BlockFace::Left);
-        let right_texture_path = block_def.get_texture_for_face(BlockFace::Right);
-        let front_texture_path = block_def.get_texture_for_face(BlockFace::Front);
-        let back_texture_path = block_def.get_texture_for_face(BlockFace::Back);
-
-        // unwrap shouldnt fail here
-        let texture_top = get_block_texture_coords(top_texture_path.unwrap()).unwrap();
-        let texture_btm = get_block_texture_coords(btm_texture_path.unwrap()).unwrap();
-        let texture_left = get_block_texture_coords(left_texture_path.unwrap()).unwrap();
-        let texture_right = get_block_texture_coords(right_texture_path.unwrap()).unwrap();
-        let texture_front = get_block_texture_coords(front_texture_path.unwrap()).unwrap();
-        let texture_back = get_block_texture_coords(back_texture_path.unwrap()).unwrap();
-
-        let block = Block {
-            id: id.clone(),
-            texture_front,
-            texture_back,
-            texture_top,
-            texture_btm,
-            texture_left,
-            texture_right,
-        };
+    }
 
-        if !block_registry.contains_key(&id.as_string()) {
-            block_registry.insert(id.as_string(), block);
+    let needed_block_defs = all_block_defs.into_iter()
+        .filter(|(_, block_def)| needed_ids.contains(&normalize_id(&block_def.id)))
+        .collect::<Vec<_>>();
 
-            println!("Registered block \"{}\"", id.as_string());
-        } else {
-            // overwrite key if it exists
-            block_registry.entry(id.as_string()).and_modify(|e| *e = block);
+    let mut changed_states = Vec::new();
 
-            println!("block \"{}\" already registered; overwriting!", id.as_string());
+    for (file_path, block_def) in resolve_block_definitions(&needed_block_defs, &mut report) {
+        if !affected_ids.contains(&normalize_id(&block_def.id)) {
+            continue;
+        }
+
+        if let Some((mut block, mut states)) = block_from_definition(&tex_coords, &models, &mut report, &file_path, &block_def) {
+            let id = block.get_identifier();
+
+            match (block_registry.get_raw_id(&id), block_registry.get(&id)) {
+                (Some(raw_id), Some(old_block)) if old_block.get_state_count() == states.len() => {
+                    block.first_state = old_block.first_state;
+
+                    for state in &mut states {
+                        state.block = raw_id;
+                    }
+
+                    changed_states.extend(block.get_states());
+
+                    state_registry.replace(block.first_state, states);
+                    block_registry.replace(&id, block);
+
+                    println!("Reloaded block \"{}\"", block_def.id);
+                },
+                (Some(_), Some(_)) => println!("Can't change the properties of block \"{}\" after startup; restart to apply them", block_def.id),
+                _ => println!("Can't register new block \"{}\" after startup; restart to add it", block_def.id)
+            }
+        }
+    }
+
+    if changed_states.is_empty() {
+        return;
+    }
+
+    for (chunk, chunk_mesh) in &chunks {
+        if changed_states.iter().any(|state_id| chunk.contains_state(*state_id)) {
+            if let Some(mesh) = meshes.get_mut(&chunk_mesh.0) {
+                *mesh = build_chunk_mesh(chunk, &block_registry, &state_registry, &colormaps);
+            }
         }
     }
 }
 
-/// Registers any blocks found in a folder relative to the `assets` folder
-pub fn register_blocks_in_dir(path: &str) {
-    // load items
-    match load_blocks_from_path(&format!("assets/{path}")) {
-        Ok(block_defs) => {
-            for block_def_res in block_defs {
-                match block_def_res {
-                    Ok(block_def) => {
-                        register_block(block_def)
-                    },
-                    Err(err) => println!("{}", err)
+/// Updates items whose definition changed on disk
+pub fn reload_item_definitions(
+    mut item_def_events: EventReader<AssetEvent<ItemDefinition>>,
+    asset_server: Res<AssetServer>,
+    item_defs: Res<Assets<ItemDefinition>>,
+    mut item_registry: ResMut<ItemRegistry>,
+    mut report: ResMut<RegistryLoadReport>,
+) {
+    for event in item_def_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            let file_path = get_handle_path(&asset_server, handle);
+
+            let item_def = match item_defs.get(handle) {
+                Some(item_def) => item_def,
+                None => continue
+            };
+
+            let id = match check_namespace(&file_path, &item_def.id) {
+                Ok(id) => id,
+                Err(err) => {
+                    report.add(err);
+                    continue;
                 }
+            };
+
+            if !check_item_definition(&mut report, &file_path, item_def) {
+                continue;
             }
-        },
-        Err(err) => println!("{}", err)
+
+            match item_registry.replace(&id, item_def.clone()) {
+                Some(_) => println!("Reloaded item \"{}\"", item_def.id),
+                None => println!("Can't register new item \"{}\" after startup; restart to add it", item_def.id)
+            }
+        }
     }
 }
 
-pub fn load_blocks_from_path(path: &str) -> Result<Vec<Result<BlockDefinition, BlockyPathError>>, BlockyPathError> {
-    let mut block_defs = Vec::new();
-    
-    let block_paths = std::fs::read_dir(path).map_err(|source|
-        BlockyPathError::DirectoryReadError(String::from(path), source)
-    )?;
+/// Creates a block and every one of its states from its definition,
+/// looking up the atlas coordinates for each of their faces and baking their models.<br>
+/// Faces with a missing texture use the generated missing texture instead,
+/// and states with a missing model are drawn as full cubes.
+///
+/// The block's `first_state` and each state's `block` are left
+/// at 0, they are set once the block is registered
+pub fn block_from_definition(
+    tex_coords: &BlockTextureCoords,
+    models: &HashMap<String, &BlockModelDefinition>,
+    report: &mut RegistryLoadReport,
+    file_path: &str,
+    block_def: &BlockDefinition
+) -> Option<(Block, Vec<BlockState>)> {
+    let id = match Identifier::from_str(&block_def.id) {
+        Ok(id) => id,
+        Err(err) => {
+            report.add(BlockyPathError::InvalidId(String::from(file_path), err));
+            return None;
+        }
+    };
+
+    let properties = block_def.get_properties();
+
+    if let Some(property) = properties.iter().find(|property| property.values.is_empty()) {
+        report.add(LoadProblem::InvalidBlockState {
+            path: String::from(file_path),
+            block: id.to_string(),
+            reason: format!("property `{}` has no values", property.name),
+        });
+
+        return None;
+    }
+
+    for variant in &block_def.variants {
+        for (name, value) in &variant.when {
+            let is_valid = properties.iter()
+                .any(|property| &property.name == name && property.values.contains(value));
+
+            if !is_valid {
+                report.warn(LoadProblem::InvalidBlockState {
+                    path: String::from(file_path),
+                    block: id.to_string(),
+                    reason: format!("variant checks for `{name}={value}`, which no state has"),
+                });
+            }
+        }
+    }
+
+    let tinted_faces = if block_def.tinted_faces.is_empty() {
+        VoxelCullCode::BFUDRL as u8
+    } else {
+        block_def.tinted_faces.iter().fold(0, |mask, face| mask | face.cull_code())
+    };
+
+    let connected = block_def.connected_textures.unwrap_or(false);
+
+    // random faces and layers are the same for every state, only warning once for each problem
+    let mut random_problems = HashSet::new();
+
+    let random_faces = BlockFace::ALL.map(|face| {
+        let random_texture = match block_def.get_random_texture(face) {
+            Some(random_texture) => random_texture,
+            None => return RandomFace::default()
+        };
+
+        let textures = random_texture.textures.iter().filter_map(|weighted| {
+            let texture_path = format!("textures/block/{}.png", weighted.texture);
+            let tex_coords = tex_coords.get(&texture_path);
+
+            if tex_coords.is_none() && random_problems.insert(texture_path.clone()) {
+                report.warn(LoadProblem::MissingTexture {
+                    path: String::from(file_path),
+                    block: id.to_string(),
+                    face,
+                    texture: texture_path,
+                });
+            }
+
+            tex_coords.map(|tex_coords| (tex_coords, weighted.weight))
+        }).collect();
+
+        let rotations = random_texture.rotations.iter().filter_map(|degrees| {
+            if degrees % 90 != 0 {
+                if random_problems.insert(format!("{degrees}")) {
+                    report.warn(LoadProblem::InvalidBlockValue {
+                        path: String::from(file_path),
+                        block: id.to_string(),
+                        reason: format!("textures can only be rotated in steps of 90 degrees, not {degrees}"),
+                    });
+                }
+
+                return None;
+            }
+
+            Some((degrees / 90 % 4) as u8)
+        }).collect();
+
+        RandomFace::new(textures, rotations)
+    });
+
+    let layers = BlockFace::ALL.map(|face| {
+        block_def.layers.iter()
+            .filter(|layer| layer.is_on_face(face))
+            .filter_map(|layer| {
+                let texture_path = format!("textures/block/{}.png", layer.texture);
+                let layer_coords = tex_coords.get(&texture_path);
+
+                if layer_coords.is_none() && random_problems.insert(texture_path.clone()) {
+                    report.warn(LoadProblem::MissingTexture {
+                        path: String::from(file_path),
+                        block: id.to_string(),
+                        face,
+                        texture: texture_path,
+                    });
+                }
+
+                layer_coords.map(|tex_coords| FaceLayer {
+                    tex_coords,
+                    tinted: layer.tinted,
+                })
+            })
+            .collect::<Vec<FaceLayer>>()
+    });
+
+    // only warn once for each missing texture or model, instead of once per state
+    let mut missing_textures = HashSet::new();
+    let mut missing_models = HashSet::new();
+    let mut missing_sheets = HashSet::new();
+
+    let states = expand_states(&properties).into_iter().map(|values| {
+        let model = block_def.get_model_for_state(&values).and_then(|model_id| {
+            let model = models.get(&normalize_id(model_id)).copied();
+
+            if model.is_none() && missing_models.insert(String::from(model_id)) {
+                report.warn(LoadProblem::MissingModel {
+                    path: String::from(file_path),
+                    block: id.to_string(),
+                    model: String::from(model_id),
+                });
+            }
 
-    for block_def_path in block_paths {
-        let block_path_res = block_def_path.map_err(|source|
-            BlockyPathError::PathReadError(String::from(path), source)
+            model
+        });
+
+        let mut get_texture_coords = |face: BlockFace, texture_path: String| {
+            tex_coords.get(&texture_path).unwrap_or_else(|| {
+                if missing_textures.insert((face, texture_path.clone())) {
+                    report.warn(LoadProblem::MissingTexture {
+                        path: String::from(file_path),
+                        block: id.to_string(),
+                        face,
+                        texture: texture_path,
+                    });
+                }
+
+                tex_coords.missing()
+            })
+        };
+
+        let [top, bottom, left, right, front, back] = BlockFace::ALL.map(|face|
+            get_texture_coords(face, block_def.get_texture_for_state(face, &values).unwrap_or_default())
         );
 
-        match block_path_res {
-            Ok(dir_entry) => {
-                let path = dir_entry.path();
-                let file_path = path.to_str().unwrap();
+        let baked_model = model.map(|model| model.bake(|model_face, face|
+            get_texture_coords(face, block_def.get_texture_for_model_face(model_face, face, &values).unwrap_or_default())
+        ));
+
+        // models are never connected, only full cube faces are
+        let connected_textures = BlockFace::ALL.map(|face| {
+            if !connected || model.is_some() {
+                return Vec::new();
+            }
+
+            let texture_path = block_def.get_texture_for_state(face, &values).unwrap_or_default();
 
+            match tex_coords.get_connected(&texture_path) {
+                Some(variants) => variants.to_vec(),
+                None => {
+                    if missing_sheets.insert(texture_path.clone()) {
+                        report.warn(LoadProblem::MissingConnectedTexture {
+                            path: String::from(file_path),
+                            block: id.to_string(),
+                            texture: texture_path,
+                        });
+                    }
 
-                match ron::from_str::<BlockDefinition>(&std::fs::read_to_string(file_path).unwrap()) {
-                    Ok(block_def) => block_defs.push(Ok(block_def)),
-                    Err(err) => block_defs.push(Err(BlockyPathError::FileParseError(String::from(file_path), err)))
+                    Vec::new()
                 }
-            },
-            Err(err) => block_defs.push(Err(err))
+            }
+        });
+
+        BlockState {
+            block: 0,
+            texture_front: front,
+            texture_back: back,
+            texture_top: top,
+            texture_btm: bottom,
+            texture_left: left,
+            texture_right: right,
+            model: baked_model,
+            full_faces: model.map_or(VoxelCullCode::BFUDRL as u8, |model| model.get_full_faces()),
+            tint: block_def.tint,
+            tinted_faces,
+            connected_textures,
+            random_faces: random_faces.clone(),
+            layers: layers.clone(),
+            values,
         }
+    }).collect();
+
+    let mut luminance = block_def.luminance.unwrap_or(0);
+
+    if luminance > MAX_LUMINANCE {
+        report.warn(LoadProblem::InvalidBlockValue {
+            path: String::from(file_path),
+            block: id.to_string(),
+            reason: format!("luminance is {luminance}, but can't be above {MAX_LUMINANCE}"),
+        });
+
+        luminance = MAX_LUMINANCE;
+    }
+
+    let hardness = block_def.hardness.unwrap_or(DEFAULT_HARDNESS);
+    let mut blast_resistance = block_def.blast_resistance.unwrap_or_else(|| hardness.max(0.));
+
+    if blast_resistance < 0. {
+        report.warn(LoadProblem::InvalidBlockValue {
+            path: String::from(file_path),
+            block: id.to_string(),
+            reason: format!("blast resistance is {blast_resistance}, but can't be negative"),
+        });
+
+        blast_resistance = 0.;
     }
 
-    Ok(block_defs)
+    let transparent = block_def.transparent.unwrap_or(false);
+
+    let block = Block {
+        id: id.intern(),
+        properties,
+        first_state: 0,
+        hardness,
+        required_tier: block_def.required_tier,
+        blast_resistance,
+        opaque: block_def.opaque.unwrap_or(!transparent),
+        transparent,
+        luminance,
+        collidable: block_def.collidable.unwrap_or(true),
+        replaceable: block_def.replaceable.unwrap_or(false),
+        block_entity: block_def.block_entity,
+    };
+
+    Some((block, states))
 }
 
-pub fn get_block_from_registry(block_id: &Identifier) -> Option<Block> {
-    get_block_from_registry_by_string(&block_id.as_string())
+/// Adds a block to the block registry,
+/// and all of its states to the state registry.<br>
+/// The item placing it is added to the item registry too, unless `has_item` is `false`
+#[allow(clippy::too_many_arguments)]
+pub fn register_block(
+    block_registry: &mut BlockRegistry,
+    state_registry: &mut BlockStateRegistry,
+    item_registry: &mut ItemRegistry,
+    tex_coords: &BlockTextureCoords,
+    models: &HashMap<String, &BlockModelDefinition>,
+    report: &mut RegistryLoadReport,
+    file_path: &str,
+    block_def: &BlockDefinition
+) {
+    if let Some((mut block, mut states)) = block_from_definition(tex_coords, models, report, file_path, block_def) {
+        let id = block.get_identifier();
+        let old_block = block_registry.get(&id).map(|old_block| (old_block.first_state, old_block.get_state_count()));
+
+        let report_error = |report: &mut RegistryLoadReport, source| report.add(LoadProblem::Registry {
+            path: String::from(file_path),
+            id: id.to_string(),
+            source,
+        });
+
+        // nothing is registered unless the block itself can be,
+        // so a failed block never leaves its states behind
+        if let Err(source) = block_registry.check_register(&id) {
+            report_error(report, source);
+            return;
+        }
+
+        // the id the block will get once it's registered
+        let raw_id = block_registry.get_raw_id(&id).unwrap_or(block_registry.len() as RawId);
+
+        for state in &mut states {
+            state.block = raw_id;
+        }
+
+        match old_block {
+            // states of a block registered again are overwritten in place,
+            // so the states of every later block keep their IDs
+            Some((first_state, state_count)) if state_count == states.len() => {
+                block.first_state = first_state;
+                state_registry.replace(first_state, states);
+            },
+            Some((_, state_count)) => {
+                report.add(LoadProblem::StateCountChanged {
+                    path: String::from(file_path),
+                    block: id.to_string(),
+                    old: state_count,
+                    new: states.len(),
+                });
+
+                return;
+            },
+            None => match state_registry.register(&id, states) {
+                Ok(first_state) => block.first_state = first_state,
+                Err(source) => {
+                    report_error(report, source);
+                    return;
+                }
+            }
+        }
+
+        match block_registry.register(&id, block) {
+            Ok(raw_id) if old_block.is_some() => println!("block \"{id}\" already registered; overwriting! (runtime id {raw_id})"),
+            Ok(raw_id) => println!("Registered block \"{id}\" (runtime id {raw_id})"),
+            Err(source) => {
+                report_error(report, source);
+                return;
+            }
+        }
+
+        // items defined in a data pack win over generated ones
+        if block_def.has_item.unwrap_or(true) && !item_registry.contains(&id) {
+            register_item(item_registry, report, file_path, ItemDefinition::block_item(&id));
+        }
+    }
 }
 
-pub fn get_block_from_registry_by_string(block_id: &str) -> Option<Block> {
-    let block_registry = BLOCK_REGISTRY.lock().unwrap();
+/// Adds every problem with `item_def` to `report`.<br>
+/// Returns `true` if it has none and can be registered
+pub fn check_item_definition(
+    report: &mut RegistryLoadReport,
+    file_path: &str,
+    item_def: &ItemDefinition
+) -> bool {
+    let errors = item_def.validate();
+    let is_valid = errors.is_empty();
 
-    if block_registry.contains_key(block_id) {
-        let registered_block = block_registry[block_id].clone();
-        
-        Some(registered_block)
-    } else {
-        None
+    for source in errors {
+        report.add(LoadProblem::InvalidItem {
+            path: String::from(file_path),
+            item: item_def.id.clone(),
+            source,
+        });
     }
+
+    is_valid
 }
 
 /// Adds an item to the item registry
-pub fn register_item(item_def: ItemDefinition) {
-    let mut item_registry = ITEM_REGISTRY.lock().unwrap();
-
+pub fn register_item(
+    item_registry: &mut ItemRegistry,
+    report: &mut RegistryLoadReport,
+    file_path: &str,
+    item_def: ItemDefinition
+) {
     let id = match Identifier::from_str(&item_def.id) {
-        Ok(id) => Some(id),
+        Ok(id) => id,
         Err(err) => {
-            println!("{err}");
-            None
+            report.add(BlockyPathError::InvalidId(String::from(file_path), err));
+            return;
         }
     };
-    
-    if let Some(id) = id {
-        if !item_registry.contains_key(&id.as_string()) {
-            item_registry.insert(id.as_string(), item_def);
 
-            println!("Registered item \"{}\"", id.as_string());
-        } else {
-            // overwrite key if it exists
-            item_registry.entry(id.as_string()).and_modify(|e| *e = item_def);
+    let already_registered = item_registry.contains(&id);
 
-            println!("item \"{}\" already registered; overwriting!", id.as_string());
-        }
+    match item_registry.register(&id, item_def) {
+        Ok(raw_id) if already_registered => println!("item \"{id}\" already registered; overwriting! (runtime id {raw_id})"),
+        Ok(raw_id) => println!("Registered item \"{id}\" (runtime id {raw_id})"),
+        Err(source) => report.add(LoadProblem::Registry {
+            path: String::from(file_path),
+            id: id.to_string(),
+            source,
+        }),
     }
 }
 
-/// Registers any items found in a folder relative to the `assets` folder
-pub fn register_items_in_dir(path: &str) {
-    // load items
-    match load_items_from_path(&format!("assets/{path}")) {
-        Ok(item_defs) => {
-            for item_def_res in item_defs {
-                match item_def_res {
-                    Ok(item_def) => {
-                        register_item(item_def)
-                    },
-                    Err(err) => println!("{}", err)
+/// Starts loading every definition found in a folder relative to the `assets` folder
+pub fn load_definitions_in_dir<T: Asset>(
+    asset_server: &AssetServer,
+    report: &mut RegistryLoadReport,
+    path: &str
+) -> Vec<Handle<T>> {
+    let mut handles = Vec::new();
+
+    // namespaces don't have to define every kind of definition
+    if !asset_server.asset_io().is_dir(Path::new(path)) {
+        return handles;
+    }
+
+    match find_definition_files(asset_server.asset_io(), path) {
+        Ok(def_paths) => {
+            for def_path_res in def_paths {
+                match def_path_res {
+                    Ok(def_path) => handles.push(asset_server.load(def_path)),
+                    Err(err) => report.add(err)
                 }
             }
         },
-        Err(err) => println!("{}", err)
+        Err(err) => report.add(err)
     }
+
+    handles
 }
 
-pub fn load_items_from_path(path: &str) -> Result<Vec<Result<ItemDefinition, BlockyPathError>>, BlockyPathError> {
-    let mut item_defs = Vec::new();
-    
-    let item_paths = std::fs::read_dir(path).map_err(|source|
+/// Returns the name of every namespace folder inside `path`
+pub fn find_data_namespaces(asset_io: &dyn AssetIo, path: &str) -> Result<Vec<String>, BlockyPathError> {
+    let namespace_paths = asset_io.read_directory(Path::new(path)).map_err(|source|
         BlockyPathError::DirectoryReadError(String::from(path), source)
     )?;
 
-    for item_def_path in item_paths {
-        let item_path_res = item_def_path.map_err(|source|
-            BlockyPathError::PathReadError(String::from(path), source)
-        );
+    let mut namespaces = namespace_paths
+        .filter(|namespace_path| asset_io.is_dir(namespace_path))
+        .filter_map(|namespace_path| namespace_path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .collect::<Vec<String>>();
 
-        match item_path_res {
-            Ok(dir_entry) => {
-                let path = dir_entry.path();
-                let file_path = path.to_str().unwrap();
+    // keep registration order stable between runs
+    namespaces.sort();
 
+    Ok(namespaces)
+}
 
-                match ron::from_str::<ItemDefinition>(&std::fs::read_to_string(file_path).unwrap()) {
-                    Ok(item_def) => item_defs.push(Ok(item_def)),
-                    Err(err) => item_defs.push(Err(BlockyPathError::FileParseError(String::from(file_path), err)))
-                }
+/// Recursively finds every `.ron` file inside `path`
+pub fn find_definition_files(asset_io: &dyn AssetIo, path: &str) -> Result<Vec<Result<PathBuf, BlockyPathError>>, BlockyPathError> {
+    let mut def_paths = Vec::new();
+
+    let dir_entries = asset_io.read_directory(Path::new(path)).map_err(|source|
+        BlockyPathError::DirectoryReadError(String::from(path), source)
+    )?;
+
+    for entry_path in dir_entries {
+        if asset_io.is_dir(&entry_path) {
+            match find_definition_files(asset_io, &entry_path.to_string_lossy()) {
+                Ok(mut sub_paths) => def_paths.append(&mut sub_paths),
+                Err(err) => def_paths.push(Err(err))
+            }
+        } else if entry_path.extension().is_some_and(|ext| ext == "ron") {
+            def_paths.push(Ok(entry_path));
+        }
+    }
+
+    // keep registration order stable between runs
+    def_paths.sort_by_key(|def_path| def_path.as_ref().ok().cloned());
+
+    Ok(def_paths)
+}
+
+/// Returns every loaded block definition along with its file path,
+/// leaving out (and reporting) any that failed to load or are in the wrong namespace
+fn collect_block_definitions<'a>(
+    asset_server: &AssetServer,
+    block_defs: &'a Assets<BlockDefinition>,
+    handles: &[Handle<BlockDefinition>],
+    report: &mut RegistryLoadReport,
+) -> Vec<(String, &'a BlockDefinition)> {
+    let mut collected = Vec::new();
+
+    for handle in handles {
+        let file_path = get_handle_path(asset_server, handle);
+
+        let block_def = block_defs.get(handle)
+            .ok_or_else(|| BlockyPathError::AssetLoadError(file_path.clone()))
+            .and_then(|block_def| {
+                check_namespace(&file_path, &block_def.id)?;
+
+                Ok(block_def)
+            });
+
+        match block_def {
+            Ok(block_def) => collected.push((file_path, block_def)),
+            Err(err) => report.add(err)
+        }
+    }
+
+    collected
+}
+
+/// Returns every loaded block model by ID
+fn collect_model_definitions<'a>(
+    asset_server: &AssetServer,
+    model_defs: &'a Assets<BlockModelDefinition>,
+    handles: &[Handle<BlockModelDefinition>],
+    report: &mut RegistryLoadReport,
+) -> HashMap<String, &'a BlockModelDefinition> {
+    let mut models = HashMap::new();
+
+    for handle in handles {
+        let file_path = get_handle_path(asset_server, handle);
+
+        let model_def = model_defs.get(handle)
+            .ok_or_else(|| BlockyPathError::AssetLoadError(file_path.clone()))
+            .and_then(|model_def| {
+                let model_id = model_id_from_path(Path::new(&file_path))
+                    .ok_or_else(|| BlockyPathError::UnknownDataFile(file_path.clone()))?;
+
+                model_id.validate().map_err(|err|
+                    BlockyPathError::InvalidId(file_path.clone(), err)
+                )?;
+
+                Ok((model_id, model_def))
+            });
+
+        match model_def {
+            Ok((model_id, model_def)) => {
+                models.insert(model_id.to_string(), model_def);
+            },
+            Err(err) => report.add(err)
+        }
+    }
+
+    models
+}
+
+/// Returns `id` followed by every block it inherits from, stopping at a cycle
+fn get_parent_chain(id: &str, parents: &HashMap<String, String>) -> Vec<String> {
+    let mut chain = vec![String::from(id)];
+
+    while let Some(parent) = parents.get(chain.last().unwrap()) {
+        if chain.contains(parent) {
+            break;
+        }
+
+        chain.push(parent.clone());
+    }
+
+    chain
+}
+
+/// Returns `id` in its `namespace:name` form if it is valid
+pub fn normalize_id(id: &str) -> String {
+    Identifier::from_str(id).map(|id| id.to_string()).unwrap_or_else(|_| String::from(id))
+}
+
+/// Maps the ID of every loaded tag to its file path and definition
+fn collect_tag_definitions<'a>(
+    asset_server: &AssetServer,
+    tag_defs: &'a Assets<TagDefinition>,
+    handles: &[Handle<TagDefinition>],
+    report: &mut RegistryLoadReport,
+) -> HashMap<Identifier, (String, &'a TagDefinition)> {
+    let mut tags = HashMap::new();
+
+    for handle in handles {
+        let file_path = get_handle_path(asset_server, handle);
+
+        let tag_def = tag_defs.get(handle)
+            .ok_or_else(|| BlockyPathError::AssetLoadError(file_path.clone()))
+            .and_then(|tag_def| {
+                let tag_id = tag_id_from_path(Path::new(&file_path))
+                    .ok_or_else(|| BlockyPathError::UnknownDataFile(file_path.clone()))?;
+
+                tag_id.validate().map_err(|err|
+                    BlockyPathError::InvalidId(file_path.clone(), err)
+                )?;
+
+                Ok((tag_id, tag_def))
+            });
+
+        match tag_def {
+            Ok((tag_id, tag_def)) => {
+                tags.insert(tag_id, (file_path, tag_def));
             },
-            Err(err) => item_defs.push(Err(err))
+            Err(err) => report.add(err)
         }
     }
 
-    Ok(item_defs)
-}
\ No newline at end of file
+    tags
+}
+
+/// Returns `true` once every handle has either loaded or failed to load
+pub fn definitions_done_loading<T: Asset>(asset_server: &AssetServer, handles: &[Handle<T>]) -> bool {
+    handles.iter().all(|handle| matches!(
+        asset_server.get_load_state(handle),
+        LoadState::Loaded | LoadState::Failed
+    ))
+}
+
+pub fn get_handle_path<T: Asset>(asset_server: &AssetServer, handle: &Handle<T>) -> String {
+    match asset_server.get_handle_path(handle) {
+        Some(path) => path.path().to_string_lossy().into_owned(),
+        None => String::from("{unknown path}")
+    }
+}
+
+/// Makes sure the id defined in `file_path` belongs to the namespace folder it was found in
+fn check_namespace(file_path: &str, id: &str) -> Result<Identifier, BlockyPathError> {
+    let id = Identifier::from_str(id).map_err(|err|
+        BlockyPathError::InvalidId(String::from(file_path), err)
+    )?;
+
+    match namespace_from_path(Path::new(file_path)) {
+        Some(namespace) if namespace != id.get_namespace() => Err(BlockyPathError::NamespaceMismatch {
+            path: String::from(file_path),
+            id: id.to_string(),
+            namespace,
+        }),
+        _ => Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+    use ron::extensions::Extensions;
+
+    use super::*;
+
+    const LEVER: &str = r#"(
+        id: "test:lever",
+        has_item: false,
+        properties: { "powered": Bool },
+    )"#;
+
+    const STONE: &str = r#"(
+        id: "test:stone",
+        has_item: false,
+    )"#;
+
+    const FACING_LEVER: &str = r#"(
+        id: "test:lever",
+        has_item: false,
+        properties: { "facing": Enum(["north", "east", "south", "west"]) },
+    )"#;
+
+    const BRIGHT_LEVER: &str = r#"(
+        id: "test:lever",
+        has_item: false,
+        luminance: 7,
+        properties: { "lit": Bool },
+    )"#;
+
+    #[derive(Default)]
+    struct Registries {
+        blocks: BlockRegistry,
+        states: BlockStateRegistry,
+        items: ItemRegistry,
+        report: RegistryLoadReport,
+    }
+
+    impl Registries {
+        fn register(&mut self, block_def: &str) {
+            let block_def = ron::Options::default()
+                .with_default_extension(Extensions::IMPLICIT_SOME)
+                .from_str::<BlockDefinition>(block_def)
+                .unwrap();
+            let tex_coords = BlockTextureCoords::new(Rect { min: Vec2::ZERO, max: Vec2::ONE });
+
+            register_block(&mut self.blocks, &mut self.states, &mut self.items, &tex_coords, &HashMap::new(), &mut self.report, "test", &block_def);
+        }
+    }
+
+    #[test]
+    fn registering_again_overwrites_states_in_place() {
+        let mut registries = Registries::default();
+        registries.register(LEVER);
+        registries.register(STONE);
+        registries.register(BRIGHT_LEVER);
+
+        assert!(registries.report.is_empty());
+        assert_eq!(registries.states.len(), 3);
+
+        let lever = registries.blocks.get(&Identifier::new("test", "lever")).unwrap();
+        let stone = registries.blocks.get(&Identifier::new("test", "stone")).unwrap();
+
+        assert_eq!(lever.first_state, 0);
+        assert_eq!(lever.get_luminance(), 7);
+        assert_eq!(stone.first_state, 2);
+    }
+
+    #[test]
+    fn changing_the_state_count_is_a_problem() {
+        let mut registries = Registries::default();
+        registries.register(LEVER);
+        registries.register(FACING_LEVER);
+
+        assert!(matches!(
+            registries.report.problems(),
+            [LoadProblem::StateCountChanged { old: 2, new: 4, .. }]
+        ));
+        assert_eq!(registries.states.len(), 2);
+        assert!(registries.blocks.get(&Identifier::new("test", "lever")).unwrap().properties.iter().any(|property| property.name == "powered"));
+    }
+
+    #[test]
+    fn failed_blocks_leave_no_states() {
+        let mut registries = Registries::default();
+        registries.blocks.freeze();
+        registries.register(LEVER);
+
+        assert!(matches!(
+            registries.report.problems(),
+            [LoadProblem::Registry { source: RegistryError::Frozen(_), .. }]
+        ));
+        assert!(registries.states.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{path::Path, str::FromStr};
+
+use bevy::reflect::TypeUuid;
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{identifier::{Identifier, InternedId}, registry::{RegistryLoadReport, LoadProblem}, BlockyPathError};
+
+/// A group of blocks or items, loaded from
+/// `assets/data/<namespace>/tags/{blocks,items}/<name>.ron`.<br>
+/// Values are IDs (`blocky:grass_block`) or other tags (`#blocky:logs`)
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "82cab658-26ed-4cda-b407-42da12f6979d"]
+pub struct TagDefinition {
+    pub values: Vec<String>,
+}
+
+/// Every block and item tag, with nested tags already flattened.<br>
+/// Tags and their contents are interned, so checking a tag never allocates
+#[derive(Default)]
+pub struct TagRegistry {
+    block_tags: HashMap<InternedId, HashSet<InternedId>>,
+    item_tags: HashMap<InternedId, HashSet<InternedId>>,
+}
+
+impl TagRegistry {
+    pub fn new(
+        block_tags: HashMap<Identifier, HashSet<Identifier>>,
+        item_tags: HashMap<Identifier, HashSet<Identifier>>
+    ) -> Self {
+        let intern = |tags: HashMap<Identifier, HashSet<Identifier>>| tags.into_iter()
+            .map(|(tag, values)| (tag.intern(), values.iter().map(InternedId::new).collect()))
+            .collect();
+
+        Self {
+            block_tags: intern(block_tags),
+            item_tags: intern(item_tags),
+        }
+    }
+
+    pub fn block_has_tag(&self, block: InternedId, tag: InternedId) -> bool {
+        self.block_tags.get(&tag)
+            .is_some_and(|blocks| blocks.contains(&block))
+    }
+
+    pub fn item_has_tag(&self, item: InternedId, tag: InternedId) -> bool {
+        self.item_tags.get(&tag)
+            .is_some_and(|items| items.contains(&item))
+    }
+
+    /// Returns the ID of every block in `tag`
+    pub fn get_block_tag(&self, tag: InternedId) -> Option<&HashSet<InternedId>> {
+        self.block_tags.get(&tag)
+    }
+
+    /// Returns the ID of every item in `tag`
+    pub fn get_item_tag(&self, tag: InternedId) -> Option<&HashSet<InternedId>> {
+        self.item_tags.get(&tag)
+    }
+}
+
+/// Returns the ID of the tag stored at `path`, relative to the `assets` folder.<br>
+/// `data/blocky/tags/blocks/logs.ron` is the tag `blocky:logs`
+pub fn tag_id_from_path(path: &Path) -> Option<Identifier> {
+    let mut components = path.with_extension("").components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .into_iter();
+
+    if components.next()? != "data" {
+        return None;
+    }
+
+    let namespace = components.next()?;
+
+    if components.next()? != "tags" {
+        return None;
+    }
+
+    // skip the `blocks`/`items` folder
+    components.next()?;
+
+    let name = components.collect::<Vec<String>>().join("/");
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(Identifier::new(&namespace, &name))
+    }
+}
+
+/// Flattens every tag into the set of IDs it contains.<br>
+/// Unknown IDs, unknown tags and cycles are added to `report` and left out
+pub fn resolve_tags(
+    tag_defs: &HashMap<Identifier, (String, &TagDefinition)>,
+    is_registered: impl Fn(&Identifier) -> bool,
+    report: &mut RegistryLoadReport,
+) -> HashMap<Identifier, HashSet<Identifier>> {
+    let mut resolved = HashMap::new();
+
+    for tag in tag_defs.keys() {
+        resolve_tag(tag, tag_defs, &is_registered, report, &mut resolved, &mut Vec::new());
+    }
+
+    resolved
+}
+
+fn resolve_tag(
+    tag: &Identifier,
+    tag_defs: &HashMap<Identifier, (String, &TagDefinition)>,
+    is_registered: &impl Fn(&Identifier) -> bool,
+    report: &mut RegistryLoadReport,
+    resolved: &mut HashMap<Identifier, HashSet<Identifier>>,
+    stack: &mut Vec<Identifier>,
+) -> HashSet<Identifier> {
+    if let Some(values) = resolved.get(tag) {
+        return values.clone();
+    }
+
+    let (path, tag_def) = match tag_defs.get(tag) {
+        Some(tag_def) => tag_def,
+        None => return HashSet::new()
+    };
+
+    // this tag is already being resolved further up, so it contains itself
+    if let Some(start) = stack.iter().position(|parent| parent == tag) {
+        let mut cycle = stack[start..].iter()
+            .map(|parent| parent.to_string())
+            .collect::<Vec<String>>();
+        cycle.push(tag.to_string());
+
+        report.add(LoadProblem::TagCycle {
+            path: path.clone(),
+            tag: tag.to_string(),
+            cycle: cycle.join(" -> "),
+        });
+
+        return HashSet::new();
+    }
+
+    stack.push(tag.clone());
+
+    let mut values = HashSet::new();
+
+    for value in &tag_def.values {
+        let (is_tag, id) = match value.strip_prefix('#') {
+            Some(nested_tag) => (true, nested_tag),
+            None => (false, value.as_str())
+        };
+
+        let id = match Identifier::from_str(id) {
+            Ok(id) => id,
+            Err(err) => {
+                report.add(BlockyPathError::InvalidId(path.clone(), err));
+                continue;
+            }
+        };
+
+        if is_tag && tag_defs.contains_key(&id) {
+            values.extend(resolve_tag(&id, tag_defs, is_registered, report, resolved, stack));
+        } else if !is_tag && is_registered(&id) {
+            values.insert(id);
+        } else {
+            report.add(LoadProblem::UnknownTagEntry {
+                path: path.clone(),
+                tag: tag.to_string(),
+                entry: value.clone(),
+            });
+        }
+    }
+
+    stack.pop();
+
+    resolved.insert(tag.clone(), values.clone());
+
+    values
+}
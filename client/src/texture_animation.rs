@@ -0,0 +1,229 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset, BoxedFuture},
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    sprite::Rect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{texture_atlas::TextureAtlasHandles, BlockyPathError};
+
+/// Extension of the files describing how a texture is animated.<br>
+/// `textures/block/water.anim.ron` animates `textures/block/water.png`
+pub const ANIMATION_EXTENSION: &str = "anim.ron";
+
+/// How a texture made of a vertical strip of square frames is animated
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "3c1d4b7e-9a2f-4e61-8d0c-5b7f2a9e4c13"]
+pub struct TextureAnimation {
+    /// Seconds each frame is shown for
+    #[serde(default = "default_frame_time")]
+    pub frame_time: f32,
+
+    /// Frames to show, counting from 0 at the top of the strip.<br>
+    /// Shows every frame from top to bottom if empty
+    #[serde(default)]
+    pub frames: Vec<usize>,
+
+    /// Whether to blend into the next frame instead of switching to it at once
+    #[serde(default)]
+    pub interpolate: bool,
+}
+
+fn default_frame_time() -> f32 { 0.1 }
+
+/// Loads `.anim.ron` files next to block textures as `TextureAnimation` assets
+#[derive(Default)]
+pub struct TextureAnimationLoader;
+
+impl AssetLoader for TextureAnimationLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().to_string_lossy().into_owned();
+
+            let animation = ron::de::from_bytes::<TextureAnimation>(bytes).map_err(|err|
+                BlockyPathError::FileParseError(path, err)
+            )?;
+
+            load_context.set_default_asset(LoadedAsset::new(animation));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[ANIMATION_EXTENSION]
+    }
+}
+
+/// An animated texture, and the tile of the block atlas it is drawn into
+pub struct AnimatedTexture {
+    /// Pixels of every frame, in the order they are shown
+    frames: Vec<Vec<u8>>,
+    frame_size: u32,
+    frame_time: f32,
+    interpolate: bool,
+
+    /// Pixel position of the texture inside the atlas
+    tile: Rect,
+
+    /// Frame and blend amount last drawn into the atlas
+    last_drawn: Option<(usize, u8)>,
+}
+
+impl AnimatedTexture {
+    /// Splits `strip` into its frames, ordered like `animation` says
+    pub fn new(strip: &Image, animation: &TextureAnimation) -> Result<Self, String> {
+        let strip = if strip.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb {
+            strip.clone()
+        } else {
+            strip.convert(TextureFormat::Rgba8UnormSrgb)
+                .ok_or_else(|| String::from("its format can't be converted to RGBA"))?
+        };
+
+        let size = strip.texture_descriptor.size;
+
+        if size.width == 0 || size.height % size.width != 0 {
+            return Err(format!("it is {}x{} pixels, which isn't a strip of square frames", size.width, size.height));
+        }
+
+        if animation.frame_time <= 0. {
+            return Err(format!("the frame time is {}, but has to be above 0", animation.frame_time));
+        }
+
+        let frame_count = (size.height / size.width) as usize;
+        let frame_bytes = (size.width * size.width * 4) as usize;
+
+        let order = if animation.frames.is_empty() {
+            (0..frame_count).collect()
+        } else {
+            animation.frames.clone()
+        };
+
+        if let Some(frame) = order.iter().find(|frame| **frame >= frame_count) {
+            return Err(format!("it has {frame_count} frames, but frame {frame} is used"));
+        }
+
+        let frames = order.into_iter()
+            .map(|frame| strip.data[frame * frame_bytes..(frame + 1) * frame_bytes].to_vec())
+            .collect();
+
+        Ok(Self {
+            frames,
+            frame_size: size.width,
+            frame_time: animation.frame_time,
+            interpolate: animation.interpolate,
+            tile: Rect::default(),
+            last_drawn: None,
+        })
+    }
+
+    /// A single frame image the atlas reserves a tile with
+    pub fn first_frame_image(&self) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.frame_size,
+                height: self.frame_size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.frames[0].clone(),
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    /// Sets the pixel position of the tile reserved for this texture in the atlas
+    pub fn set_tile(&mut self, tile: Rect) {
+        self.tile = tile;
+    }
+
+    /// Returns the pixels to show `seconds` into the animation,
+    /// or `None` if they are the same as the last ones drawn
+    fn get_pixels(&mut self, seconds: f64) -> Option<Vec<u8>> {
+        let progress = seconds / self.frame_time as f64;
+        let frame = progress as usize % self.frames.len();
+
+        let blend = if self.interpolate {
+            (progress.fract() * 255.) as u8
+        } else {
+            0
+        };
+
+        if self.last_drawn == Some((frame, blend)) {
+            return None;
+        }
+
+        self.last_drawn = Some((frame, blend));
+
+        let current = &self.frames[frame];
+
+        if blend == 0 {
+            return Some(current.clone());
+        }
+
+        let next = &self.frames[(frame + 1) % self.frames.len()];
+
+        Some(current.iter().zip(next)
+            .map(|(from, to)| {
+                let from = *from as u32;
+                let to = *to as u32;
+
+                ((from * (255 - blend as u32) + to * blend as u32) / 255) as u8
+            })
+            .collect())
+    }
+}
+
+/// Every animated block texture
+#[derive(Default)]
+pub struct AnimatedTextures(pub Vec<AnimatedTexture>);
+
+/// Draws the current frame of every animated texture into the block atlas
+pub fn animate_textures(
+    time: Res<Time>,
+    mut animated_textures: ResMut<AnimatedTextures>,
+    our_atlases: Res<TextureAtlasHandles>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    let atlas = match our_atlases.block_atlas.as_ref().and_then(|handle| texture_atlases.get(handle)) {
+        Some(atlas) => atlas,
+        None => return
+    };
+
+    // only touch the atlas if a frame changed, since that re-uploads it
+    let mut frames = animated_textures.0.iter_mut()
+        .filter_map(|animated| {
+            let pixels = animated.get_pixels(time.seconds_since_startup())?;
+
+            Some((animated.tile, animated.frame_size, pixels))
+        })
+        .peekable();
+
+    if frames.peek().is_none() {
+        return;
+    }
+
+    let atlas_texture = match textures.get_mut(&atlas.texture) {
+        Some(atlas_texture) => atlas_texture,
+        None => return
+    };
+
+    let atlas_width = atlas_texture.texture_descriptor.size.width as usize;
+
+    for (tile, frame_size, pixels) in frames {
+        let row_bytes = frame_size as usize * 4;
+
+        for row in 0..frame_size as usize {
+            let start = ((tile.min.y as usize + row) * atlas_width + tile.min.x as usize) * 4;
+
+            atlas_texture.data[start..start + row_bytes]
+                .copy_from_slice(&pixels[row * row_bytes..(row + 1) * row_bytes]);
+        }
+    }
+}
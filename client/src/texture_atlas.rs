@@ -1,15 +1,31 @@
 use bevy::{
     asset::LoadState,
-    prelude::*, sprite::{TextureAtlasBuilderError, Rect}
+    prelude::*, sprite::{TextureAtlasBuilderError, Rect},
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat}
 };
 use iyes_loopless::prelude::*;
 
-use crate::{AppState, registry::register_block_texture_coords};
+use hashbrown::HashMap;
+
+use crate::{
+    AppState, BlockyPathError,
+    item_icon::{IconFaces, build_block_icon},
+    registry::{BlockTextureCoords, ItemTextureCoords, ItemRegistry, BlockRegistry, BlockStateRegistry, RegistryLoadReport, LoadProblem, definitions_done_loading, get_handle_path},
+    connected_texture::{build_connected_variants, CONNECTED_EXTENSION},
+    tint::{Colormap, Colormaps, GRASS_COLORMAP_PATH, FOLIAGE_COLORMAP_PATH},
+    texture_animation::{TextureAnimation, TextureAnimationLoader, AnimatedTexture, AnimatedTextures, animate_textures, ANIMATION_EXTENSION},
+};
 
 #[derive(Default)]
 pub struct TextureHandles {
     pub block_texture_handles: Vec<HandleUntyped>,
     pub item_texture_handles: Vec<HandleUntyped>,
+
+    /// `.anim.ron` files found next to block textures
+    pub block_animation_handles: Vec<Handle<TextureAnimation>>,
+
+    pub grass_colormap: Handle<Image>,
+    pub foliage_colormap: Handle<Image>,
 }
 
 #[derive(PartialEq)]
@@ -24,7 +40,13 @@ impl Plugin for TextureAtlasesPlugin {
     fn build(&self, app: &mut App) {
 		app.insert_resource(TextureAtlasHandles::default())
            .insert_resource(TextureBuildState::LoadTextures)
+           .add_asset::<TextureAnimation>()
+           .init_asset_loader::<TextureAnimationLoader>()
+           .init_resource::<AnimatedTextures>()
+           .init_resource::<Colormaps>()
            .add_enter_system(AppState::LoadResources, load_textures)
+           .add_exit_system(AppState::Registry, build_item_atlas)
+           .add_system(animate_textures.run_in_state(AppState::Finished))
            .add_system_set(
               ConditionSet::new()
                 .run_in_state(AppState::LoadResources)
@@ -40,9 +62,22 @@ pub fn load_textures(
     mut texture_handles: ResMut<TextureHandles>,
     asset_server: Res<AssetServer>,
 ) {
-    texture_handles.block_texture_handles = asset_server.load_folder("textures/block").unwrap();
+    // animation descriptors live next to the textures they animate
+    let (animation_handles, block_texture_handles) = asset_server.load_folder("textures/block").unwrap()
+        .into_iter()
+        .partition::<Vec<HandleUntyped>, _>(|handle| asset_server.get_handle_path(handle)
+            .is_some_and(|path| path.path().to_string_lossy().ends_with(ANIMATION_EXTENSION))
+        );
+
+    texture_handles.block_texture_handles = block_texture_handles;
+    texture_handles.block_animation_handles = animation_handles.into_iter()
+        .map(|handle| handle.typed())
+        .collect();
     texture_handles.item_texture_handles = asset_server.load_folder("textures/item").unwrap();
 
+    texture_handles.grass_colormap = asset_server.load(GRASS_COLORMAP_PATH);
+    texture_handles.foliage_colormap = asset_server.load(FOLIAGE_COLORMAP_PATH);
+
     /*for handle in &texture_handles.block_texture_handles {
         if let Some(img) = asset_server.get_handle_path(handle) {
             if let Some(label) = img.label() {
@@ -72,7 +107,9 @@ pub fn check_textures(
     );
     
     if LoadState::Loaded == block_textures_states &&
-       LoadState::Loaded == item_textures_states {
+       LoadState::Loaded == item_textures_states &&
+       definitions_done_loading(&asset_server, &texture_handles.block_animation_handles) &&
+       definitions_done_loading(&asset_server, &[texture_handles.grass_colormap.clone(), texture_handles.foliage_colormap.clone()]) {
         *texture_build_state = TextureBuildState::BuildAtlas;
     }
 }
@@ -88,6 +125,7 @@ impl Default for TextureAtlasHandles {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_texture_atlas(
     mut commands: Commands,
     texture_build_state: Res<TextureBuildState>,
@@ -96,14 +134,69 @@ pub fn build_texture_atlas(
     mut our_atlases: ResMut<TextureAtlasHandles>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut textures: ResMut<Assets<Image>>,
+    animations: Res<Assets<TextureAnimation>>,
+    mut report: ResMut<RegistryLoadReport>,
 ) {
     if *texture_build_state != TextureBuildState::BuildAtlas {
         return;
     }
 
+    // always available, for any block face whose texture can't be found
+    let missing_texture_handle = textures.add(missing_texture_image());
+
+    let mut animated_textures = Vec::new();
+    let mut connected_textures = Vec::new();
+    let mut block_texture_handles = Vec::new();
+
+    let animations = collect_animations(&asset_server, &animations, &texture_handles.block_animation_handles, &mut report);
+
+    // animated textures only get one tile in the atlas, for their first frame
+    for handle in &texture_handles.block_texture_handles {
+        let tex_path = get_handle_path(&asset_server, &handle.clone().typed_weak::<Image>());
+
+        // connected texture sheets are replaced by every variant built from them
+        if tex_path.ends_with(CONNECTED_EXTENSION) {
+            let variants = textures.get(&handle.clone().typed_weak())
+                .map(|sheet| build_connected_variants(sheet)
+                    .map_err(|reason| report.add(LoadProblem::InvalidConnectedTexture { path: tex_path.clone(), reason }))
+                );
+
+            if let Some(Ok(variants)) = variants {
+                let variant_handles = variants.into_iter()
+                    .map(|variant| textures.add(variant))
+                    .collect::<Vec<Handle<Image>>>();
+
+                block_texture_handles.extend(variant_handles.iter().map(|variant| variant.clone_untyped()));
+                connected_textures.push((tex_path.trim_end_matches(CONNECTED_EXTENSION).to_owned() + "png", variant_handles));
+
+                continue;
+            }
+        }
+
+        let animated = animations.get(&tex_path).and_then(|animation| {
+            let strip = textures.get(&handle.clone().typed_weak())?;
+
+            AnimatedTexture::new(strip, animation)
+                .map_err(|reason| report.add(LoadProblem::InvalidAnimation { path: tex_path.clone(), reason }))
+                .ok()
+        });
+
+        match animated {
+            Some(animated) => {
+                let frame_handle = textures.add(animated.first_frame_image());
+
+                block_texture_handles.push(frame_handle.clone_untyped());
+                animated_textures.push((tex_path, frame_handle, animated));
+            },
+            None => block_texture_handles.push(handle.clone())
+        }
+    }
+
+    block_texture_handles.push(missing_texture_handle.clone_untyped());
+
     let block_texture_atlas = build_atlas(
         &asset_server,
-        &texture_handles.block_texture_handles,
+        &block_texture_handles,
         &mut textures
     ).unwrap();
     
@@ -114,28 +207,149 @@ pub fn build_texture_atlas(
 
     our_atlases.block_atlas = Some(block_atlas_handle);
 
+    let mut tex_coords = BlockTextureCoords::new(
+        get_atlas_coords(&block_texture_atlas, &missing_texture_handle).unwrap()
+    );
+
     for (texture_handle, _) in &block_texture_atlas.texture_handles.clone().unwrap() {
         if let Some(asset_path) = asset_server.get_handle_path(texture_handle) {
             let tex_path = String::from(asset_path.path().to_str().unwrap());
-            register_block_texture_coords(tex_path, &block_texture_atlas, &asset_server);
+
+            match get_atlas_coords(&block_texture_atlas, texture_handle) {
+                Some(coords) => tex_coords.register(tex_path, coords),
+                None => println!("[Error] \"{tex_path}\" is missing from the block atlas")
+            }
         }
     }
-    
+
+    for (tex_path, frame_handle, animated) in &mut animated_textures {
+        if let Some(tex_index) = block_texture_atlas.get_texture_index(&*frame_handle) {
+            tex_coords.register(tex_path.clone(), get_atlas_coords(&block_texture_atlas, frame_handle).unwrap());
+            animated.set_tile(block_texture_atlas.textures[tex_index]);
+        }
+    }
+
+    for (tex_path, variant_handles) in connected_textures {
+        let variants = variant_handles.iter()
+            .map(|variant| get_atlas_coords(&block_texture_atlas, variant))
+            .collect::<Option<Vec<Rect>>>();
+
+        match variants {
+            Some(variants) => tex_coords.register_connected(tex_path, variants),
+            None => println!("[Error] variants of \"{tex_path}\" are missing from the block atlas")
+        }
+    }
+
+    commands.insert_resource(tex_coords);
+    commands.insert_resource(build_colormaps(&texture_handles, &textures, &mut report));
+    commands.insert_resource(AnimatedTextures(
+        animated_textures.into_iter().map(|(_, _, animated)| animated).collect()
+    ));
+
+    commands.insert_resource(NextState(AppState::Registry))
+}
+
+/// Builds the item atlas once every item is registered.<br>
+/// Items use their texture in `textures/item`, while
+/// block items get an isometric icon drawn from their block's faces
+#[allow(clippy::too_many_arguments)]
+pub fn build_item_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    texture_handles: Res<TextureHandles>,
+    mut our_atlases: ResMut<TextureAtlasHandles>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut textures: ResMut<Assets<Image>>,
+    item_registry: Res<ItemRegistry>,
+    block_registry: Res<BlockRegistry>,
+    state_registry: Res<BlockStateRegistry>,
+    colormaps: Res<Colormaps>,
+) {
+    let missing_texture_handle = textures.add(missing_texture_image());
+
+    // icons read many tiles out of the block atlas, so it's converted to RGBA only once
+    let block_atlas_image = our_atlases.block_atlas.as_ref()
+        .and_then(|handle| texture_atlases.get(handle))
+        .and_then(|atlas| textures.get(&atlas.texture))
+        .and_then(|image| if image.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb {
+            Some(image.clone())
+        } else {
+            image.convert(TextureFormat::Rgba8UnormSrgb)
+        });
+
+    let mut icon_handles = Vec::new();
+
+    for (_, item_def) in item_registry.iter() {
+        let faces = item_def.get_placed_block()
+            .and_then(|block_id| block_registry.get(&block_id))
+            .and_then(|block| state_registry.get(block.get_default_state()))
+            .map(|state| IconFaces::from_state(state, &colormaps));
+
+        if let (Some(atlas_image), Some(faces)) = (&block_atlas_image, faces) {
+            match build_block_icon(atlas_image, &faces) {
+                Some(icon) => icon_handles.push((item_def.id.clone(), textures.add(icon))),
+                None => println!("[Error] couldn't make an icon for block item \"{}\"", item_def.id)
+            }
+        }
+    }
+
+    let mut item_texture_handles = texture_handles.item_texture_handles.clone();
+
+    item_texture_handles.extend(icon_handles.iter().map(|(_, handle)| handle.clone_untyped()));
+    item_texture_handles.push(missing_texture_handle.clone_untyped());
 
     let item_texture_atlas = build_atlas(
         &asset_server,
-        &texture_handles.item_texture_handles,
+        &item_texture_handles,
         &mut textures
     ).unwrap();
 
+    let mut item_tex_coords = ItemTextureCoords::new(
+        get_atlas_coords(&item_texture_atlas, &missing_texture_handle).unwrap()
+    );
+
+    for handle in &texture_handles.item_texture_handles {
+        let handle = handle.clone().typed_weak::<Image>();
+
+        if let Some(coords) = get_atlas_coords(&item_texture_atlas, &handle) {
+            item_tex_coords.register(get_handle_path(&asset_server, &handle), coords);
+        }
+    }
+
+    for (item_id, handle) in icon_handles {
+        if let Some(coords) = get_atlas_coords(&item_texture_atlas, &handle) {
+            item_tex_coords.register_icon(item_id, coords);
+        }
+    }
+
     //let item_texture_atlas_texture = item_texture_atlas.texture.clone();
     //let itemtest_block_handle = asset_server.get_handle("textures/item/item_test.png");
     //let itemtest_block_index = item_texture_atlas.get_texture_index(&itemtest_block_handle).unwrap();
-    let item_atlas_handle = texture_atlases.add(item_texture_atlas);
+    our_atlases.item_atlas = Some(texture_atlases.add(item_texture_atlas));
 
-    our_atlases.item_atlas = Some(item_atlas_handle);
+    commands.insert_resource(item_tex_coords);
+}
 
-    commands.insert_resource(NextState(AppState::Registry))
+/// Pixel position and size of the texture at `coords` in an atlas image.<br>
+/// Returns `None` if the atlas isn't `Rgba8UnormSrgb`, or the texture is empty or out of bounds
+pub fn get_atlas_pixel_rect(atlas_image: &Image, coords: Rect) -> Option<(UVec2, UVec2)> {
+    if atlas_image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+        return None;
+    }
+
+    let atlas_size = atlas_image.size();
+
+    // `coords` are between 0 and 1
+    let min = (coords.min * atlas_size).round();
+    let max = (coords.max * atlas_size).round().min(atlas_size);
+
+    let size = (max - min).as_uvec2();
+
+    if size.x == 0 || size.y == 0 || atlas_image.data.len() < (atlas_size.x * atlas_size.y * 4.) as usize {
+        return None;
+    }
+
+    Some((min.as_uvec2(), size))
 }
 
 /// Returned when there is an error when loading textures/
@@ -176,6 +390,100 @@ fn build_atlas(
     }
 }
 
+/// Reads the grass and foliage colormaps,
+/// using flat colors for any that couldn't be loaded
+fn build_colormaps(
+    texture_handles: &TextureHandles,
+    textures: &Assets<Image>,
+    report: &mut RegistryLoadReport,
+) -> Colormaps {
+    let mut colormaps = Colormaps::default();
+
+    let mut read_colormap = |handle: &Handle<Image>, path: &str| {
+        let colormap = textures.get(handle).and_then(Colormap::from_image);
+
+        if colormap.is_none() {
+            report.warn(BlockyPathError::AssetLoadError(String::from(path)));
+        }
+
+        colormap
+    };
+
+    if let Some(grass) = read_colormap(&texture_handles.grass_colormap, GRASS_COLORMAP_PATH) {
+        colormaps.grass = grass;
+    }
+
+    if let Some(foliage) = read_colormap(&texture_handles.foliage_colormap, FOLIAGE_COLORMAP_PATH) {
+        colormaps.foliage = foliage;
+    }
+
+    colormaps
+}
+
+/// Returns every loaded texture animation, keyed by the path of the texture it animates
+fn collect_animations<'a>(
+    asset_server: &AssetServer,
+    animations: &'a Assets<TextureAnimation>,
+    handles: &[Handle<TextureAnimation>],
+    report: &mut RegistryLoadReport,
+) -> HashMap<String, &'a TextureAnimation> {
+    let mut collected = HashMap::new();
+
+    for handle in handles {
+        let anim_path = get_handle_path(asset_server, handle);
+
+        match animations.get(handle) {
+            Some(animation) => {
+                let tex_path = anim_path.trim_end_matches(ANIMATION_EXTENSION).to_owned() + "png";
+
+                collected.insert(tex_path, animation);
+            },
+            None => report.add(BlockyPathError::AssetLoadError(anim_path))
+        }
+    }
+
+    collected
+}
+
+/// Returns the UV coordinates of a texture inside `atlas`
+pub fn get_atlas_coords(atlas: &TextureAtlas, texture_handle: &Handle<Image>) -> Option<Rect> {
+    atlas.get_texture_index(texture_handle)
+        .map(|tex_index| atlas_coords_fix(atlas.textures[tex_index], atlas.size))
+}
+
+/// Size in pixels of the generated missing texture
+pub const MISSING_TEXTURE_SIZE: u32 = 16;
+
+/// Generates a magenta and black checkerboard texture,
+/// used for block faces whose texture can't be found
+pub fn missing_texture_image() -> Image {
+    let half_size = MISSING_TEXTURE_SIZE / 2;
+    let mut data = Vec::with_capacity((MISSING_TEXTURE_SIZE * MISSING_TEXTURE_SIZE * 4) as usize);
+
+    for y in 0..MISSING_TEXTURE_SIZE {
+        for x in 0..MISSING_TEXTURE_SIZE {
+            let is_magenta = (x < half_size) == (y < half_size);
+
+            if is_magenta {
+                data.extend_from_slice(&[255, 0, 255, 255]);
+            } else {
+                data.extend_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: MISSING_TEXTURE_SIZE,
+            height: MISSING_TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
 /// Makes sure that texture UVs are between 0 and 1
 pub fn atlas_coords_fix(texture_pos: Rect, size: Vec2) -> Rect {
     Rect {
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::procedural::Climate;
+
+pub const GRASS_COLORMAP_PATH: &str = "textures/colormap/grass.png";
+pub const FOLIAGE_COLORMAP_PATH: &str = "textures/colormap/foliage.png";
+
+/// Vertex color of faces that aren't tinted
+pub const NO_TINT: [f32; 4] = [1., 1., 1., 1.];
+
+/// How the faces of a block are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlockTint {
+    /// The same color everywhere, as sRGB `(red, green, blue)`
+    Fixed(u8, u8, u8),
+
+    /// Looked up in the grass colormap from the climate the block is in
+    Grass,
+
+    /// Looked up in the foliage colormap from the climate the block is in
+    Foliage,
+}
+
+/// A color for every climate, sampled from an image.<br>
+/// Hotter climates are to the left and wetter climates are to the top
+#[derive(Clone)]
+pub struct Colormap {
+    width: usize,
+    height: usize,
+
+    /// Linear RGBA color of every pixel
+    colors: Arc<Vec<[f32; 4]>>,
+}
+
+impl Colormap {
+    /// Returns `None` if `image` can't be read as RGBA
+    pub fn from_image(image: &Image) -> Option<Self> {
+        let image = image.convert(TextureFormat::Rgba8UnormSrgb)?;
+        let size = image.texture_descriptor.size;
+
+        let colors = image.data.chunks_exact(4)
+            .map(|pixel| Color::rgba_u8(pixel[0], pixel[1], pixel[2], pixel[3]).as_linear_rgba_f32())
+            .collect();
+
+        Some(Self {
+            width: size.width as usize,
+            height: size.height as usize,
+            colors: Arc::new(colors),
+        })
+    }
+
+    /// A colormap with the same color for every climate
+    pub fn flat(color: Color) -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            colors: Arc::new(vec![color.as_linear_rgba_f32()]),
+        }
+    }
+
+    pub fn sample(&self, climate: Climate) -> [f32; 4] {
+        let temperature = climate.temperature.clamp(0., 1.);
+
+        // it can only be as wet as it is warm
+        let humidity = climate.humidity.clamp(0., 1.) * temperature;
+
+        let x = ((1. - temperature) * (self.width - 1) as f32) as usize;
+        let y = ((1. - humidity) * (self.height - 1) as f32) as usize;
+
+        self.colors[y * self.width + x]
+    }
+}
+
+/// The colormaps used by `BlockTint::Grass` and `BlockTint::Foliage`
+#[derive(Clone)]
+pub struct Colormaps {
+    pub grass: Colormap,
+    pub foliage: Colormap,
+}
+
+impl Default for Colormaps {
+    /// Flat colors, for when the colormap images can't be loaded
+    fn default() -> Self {
+        Self {
+            grass: Colormap::flat(Color::rgb_u8(127, 178, 56)),
+            foliage: Colormap::flat(Color::rgb_u8(72, 181, 24)),
+        }
+    }
+}
+
+impl Colormaps {
+    /// Returns the vertex color for a face tinted with `tint` in `climate`
+    pub fn get_color(&self, tint: BlockTint, climate: Climate) -> [f32; 4] {
+        match tint {
+            BlockTint::Fixed(red, green, blue) => Color::rgb_u8(red, green, blue).as_linear_rgba_f32(),
+            BlockTint::Grass => self.grass.sample(climate),
+            BlockTint::Foliage => self.foliage.sample(climate),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use bevy::{prelude::*, diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin}};
-use bevy_egui::{EguiContext, egui::{DragValue, Slider}};
-use iyes_loopless::prelude::ConditionSet;
+use bevy_egui::{EguiContext, egui::{DragValue, Slider, ScrollArea}};
+use iyes_loopless::prelude::*;
 
-use crate::{player_cam::PlayerCamera, AppState};
+use crate::{player_cam::PlayerCamera, registry::RegistryLoadReport, AppState};
 
 #[derive(Component)]
 pub struct FpsText;
@@ -25,7 +25,8 @@ impl Plugin for UIPlugin {
               .with_system(draw_player_pos)
               .with_system(draw_player_facing_dir)
               .into()
-        );
+        )
+        .add_system(draw_load_report.run_in_state(AppState::LoadError));
 	}
 }
 
@@ -89,6 +90,41 @@ pub fn ui_world_gen(
     }
 }
 
+/// Lists everything that went wrong while loading the data packs
+pub fn draw_load_report(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    report: Res<RegistryLoadReport>,
+) {
+    bevy_egui::egui::Window::new("Load Errors").show(egui_context.ctx_mut(), |ui| {
+        ui.label(format!("{} problem(s) were found while loading data packs.", report.problems().len()));
+        ui.label("Anything listed below was skipped.");
+
+        ui.separator();
+
+        ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+            for problem in report.problems() {
+                ui.label(problem.to_string());
+            }
+
+            if !report.warnings().is_empty() {
+                ui.separator();
+                ui.label(format!("{} warning(s):", report.warnings().len()));
+
+                for warning in report.warnings() {
+                    ui.label(warning.to_string());
+                }
+            }
+        });
+
+        ui.separator();
+
+        if ui.button("Continue anyway").clicked() {
+            commands.insert_resource(NextState(AppState::Finished));
+        }
+    });
+}
+
 pub fn draw_player_pos(
     query: Query<&GlobalTransform, With<PlayerCamera>>,
     mut text_query: Query<&mut Text, With<PlayerPosText>>